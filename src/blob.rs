@@ -0,0 +1,357 @@
+//! Streaming of large blobs, e.g. blocks or transactions, without holding them in memory.
+//!
+//! [`ReaderBlob`] serializes a blob whose contents are read from an [`io::Read`] source while
+//! writing the output. [`StreamedBlob`] is its counterpart for deserialization: used together with
+//! [`Config::from_reader_with_blob_sink`](crate::Config::from_reader_with_blob_sink), the contents of
+//! the blob are copied into an [`io::Write`] sink while reading the input.
+//!
+//! ```
+//! use monero_epee_bin_serde::blob::{ReaderBlob, StreamedBlob};
+//! use monero_epee_bin_serde::Config;
+//! use serde::{Deserialize, Serialize};
+//!
+//! #[derive(Serialize)]
+//! struct Outgoing<R: std::io::Read> {
+//!     block: ReaderBlob<R>,
+//! }
+//!
+//! #[derive(Deserialize)]
+//! struct Incoming {
+//!     block: StreamedBlob,
+//! }
+//!
+//! let block = vec![7u8; 100_000];
+//! let message = Outgoing {
+//!     block: ReaderBlob::new(block.len(), block.as_slice()),
+//! };
+//!
+//! let mut bytes = Vec::new();
+//! Config::new().to_writer(&mut bytes, &message).unwrap();
+//!
+//! let mut archive = Vec::new();
+//! let incoming: Incoming = Config::new()
+//!     .from_reader_with_blob_sink(bytes.as_slice(), &mut archive)
+//!     .unwrap();
+//!
+//! assert_eq!(incoming.block.len(), block.len());
+//! assert_eq!(archive, block);
+//! ```
+
+use serde::de::{SeqAccess, Visitor};
+use serde::ser::{Error as _, SerializeTupleStruct};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::cell::RefCell;
+use std::fmt;
+use std::io;
+
+/// Name of the types that the serializer and deserializer treat as streamed blobs.
+pub(crate) const TOKEN: &str = "$monero_epee_bin_serde::private::Blob";
+
+/// The size of the chunks a [`ReaderBlob`] is read in.
+const CHUNK_SIZE: usize = 8 * 1024;
+
+/// A blob whose contents are read from `R` during serialization.
+///
+/// The length of the blob has to be known upfront, as epee prefixes blobs with their length.
+/// Serialization fails if the source ends before `length` bytes were read. As serializing reads
+/// the source, a `ReaderBlob` can only be serialized once. [`serialized_size`](crate::serialized_size)
+/// doesn't read the source and can be used before serializing.
+///
+/// Other data formats see a tuple struct whose fields are the chunks of the blob.
+pub struct ReaderBlob<R> {
+    length: usize,
+    reader: RefCell<R>,
+}
+
+impl<R> ReaderBlob<R>
+where
+    R: io::Read,
+{
+    /// Creates a blob of `length` bytes that are read from `reader`.
+    pub fn new(length: usize, reader: R) -> Self {
+        Self {
+            length,
+            reader: RefCell::new(reader),
+        }
+    }
+
+    /// The length of the blob in bytes.
+    pub fn len(&self) -> usize {
+        self.length
+    }
+
+    /// Whether the blob is empty.
+    pub fn is_empty(&self) -> bool {
+        self.length == 0
+    }
+
+    /// Returns the source of the blob.
+    pub fn into_inner(self) -> R {
+        self.reader.into_inner()
+    }
+}
+
+impl<R> Serialize for ReaderBlob<R>
+where
+    R: io::Read,
+{
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut blob = serializer.serialize_tuple_struct(TOKEN, self.length)?;
+
+        let mut remaining = self.length;
+        while remaining > 0 {
+            let length = remaining.min(CHUNK_SIZE);
+            blob.serialize_field(&Chunk {
+                reader: &self.reader,
+                length,
+            })?;
+            remaining -= length;
+        }
+
+        blob.end()
+    }
+}
+
+/// The next `length` bytes of the source of a [`ReaderBlob`], which are only read when serialized.
+struct Chunk<'a, R> {
+    reader: &'a RefCell<R>,
+    length: usize,
+}
+
+impl<'a, R> Serialize for Chunk<'a, R>
+where
+    R: io::Read,
+{
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut buffer = [0u8; CHUNK_SIZE];
+        let chunk = &mut buffer[..self.length];
+
+        self.reader
+            .borrow_mut()
+            .read_exact(chunk)
+            .map_err(|e| S::Error::custom(format_args!("failed to read blob: {}", e)))?;
+
+        serializer.serialize_bytes(chunk)
+    }
+}
+
+/// A blob whose contents were copied into the sink passed to
+/// [`Config::from_reader_with_blob_sink`](crate::Config::from_reader_with_blob_sink).
+///
+/// Only the length of the blob is retained. Without a sink, e.g. when using
+/// [`from_bytes`](crate::from_bytes), the contents are skipped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StreamedBlob {
+    length: usize,
+}
+
+impl StreamedBlob {
+    /// The length of the blob in bytes.
+    pub fn len(&self) -> usize {
+        self.length
+    }
+
+    /// Whether the blob is empty.
+    pub fn is_empty(&self) -> bool {
+        self.length == 0
+    }
+}
+
+impl<'de> Deserialize<'de> for StreamedBlob {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_newtype_struct(TOKEN, StreamedBlobVisitor)
+    }
+}
+
+struct StreamedBlobVisitor;
+
+impl<'de> Visitor<'de> for StreamedBlobVisitor {
+    type Value = StreamedBlob;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "a blob")
+    }
+
+    // the length of the blob after its contents were copied to the sink
+    fn visit_u64<E>(self, v: u64) -> Result<Self::Value, E>
+    where
+        E: serde::de::Error,
+    {
+        let length = usize::try_from(v).map_err(E::custom)?;
+
+        Ok(StreamedBlob { length })
+    }
+
+    // other data formats
+    fn visit_newtype_struct<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_bytes(self)
+    }
+
+    fn visit_bytes<E>(self, v: &[u8]) -> Result<Self::Value, E>
+    where
+        E: serde::de::Error,
+    {
+        Ok(StreamedBlob { length: v.len() })
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        let mut length = 0;
+        while seq.next_element::<u8>()?.is_some() {
+            length += 1;
+        }
+
+        Ok(StreamedBlob { length })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{from_bytes, serialized_size, to_bytes, Config};
+
+    #[derive(Serialize)]
+    struct Outgoing<R: io::Read> {
+        before: u8,
+        blob: ReaderBlob<R>,
+        after: u8,
+    }
+
+    #[derive(Deserialize, Serialize)]
+    struct Incoming {
+        before: u8,
+        blob: Vec<u8>,
+        after: u8,
+    }
+
+    #[derive(Debug, Deserialize)]
+    struct IncomingStreamed {
+        before: u8,
+        blob: StreamedBlob,
+        after: u8,
+    }
+
+    fn blob() -> Vec<u8> {
+        (0..3 * CHUNK_SIZE + 5).map(|i| i as u8).collect()
+    }
+
+    #[test]
+    fn reader_blob_is_a_plain_blob() {
+        let blob = blob();
+
+        let streamed = to_bytes(&Outgoing {
+            before: 1,
+            blob: ReaderBlob::new(blob.len(), blob.as_slice()),
+            after: 2,
+        })
+        .unwrap();
+        let buffered = to_bytes(&Incoming {
+            before: 1,
+            blob: blob.clone(),
+            after: 2,
+        })
+        .unwrap();
+        assert_eq!(streamed, buffered);
+
+        let decoded: Incoming = from_bytes(&streamed).unwrap();
+        assert_eq!(decoded.blob, blob);
+    }
+
+    #[test]
+    fn serialized_size_does_not_read_the_source() {
+        let blob = blob();
+        let mut source = blob.as_slice();
+
+        let message = Outgoing {
+            before: 1,
+            blob: ReaderBlob::new(blob.len(), &mut source),
+            after: 2,
+        };
+        let size = serialized_size(&message).unwrap();
+        assert_eq!(size, to_bytes(&message).unwrap().len());
+        assert!(source.is_empty());
+    }
+
+    #[derive(Serialize)]
+    struct Positional<R: io::Read>(u8, ReaderBlob<R>);
+
+    #[derive(Serialize)]
+    struct Nested<R: io::Read> {
+        blobs: Vec<Vec<ReaderBlob<R>>>,
+        positional: Positional<R>,
+    }
+
+    #[test]
+    fn serialized_size_counts_buffered_blobs() {
+        let blob = blob();
+
+        let message = Nested {
+            blobs: vec![
+                vec![ReaderBlob::new(blob.len(), blob.as_slice())],
+                vec![
+                    ReaderBlob::new(3, blob.as_slice()),
+                    ReaderBlob::new(blob.len(), blob.as_slice()),
+                ],
+            ],
+            positional: Positional(1, ReaderBlob::new(blob.len(), blob.as_slice())),
+        };
+        let size = serialized_size(&message).unwrap();
+        assert_eq!(size, to_bytes(&message).unwrap().len());
+    }
+
+    #[test]
+    fn short_source_fails() {
+        let blob = blob();
+
+        to_bytes(&Outgoing {
+            before: 1,
+            blob: ReaderBlob::new(blob.len() + 1, blob.as_slice()),
+            after: 2,
+        })
+        .unwrap_err();
+    }
+
+    #[test]
+    fn streamed_blob_is_copied_into_sink() {
+        let blob = blob();
+        let bytes = to_bytes(&Incoming {
+            before: 1,
+            blob: blob.clone(),
+            after: 2,
+        })
+        .unwrap();
+
+        let mut sink = Vec::new();
+        let decoded: IncomingStreamed = Config::new()
+            .from_reader_with_blob_sink(bytes.as_slice(), &mut sink)
+            .unwrap();
+        assert_eq!((decoded.before, decoded.after), (1, 2));
+        assert_eq!(decoded.blob.len(), blob.len());
+        assert_eq!(sink, blob);
+
+        let decoded: IncomingStreamed = from_bytes(&bytes).unwrap();
+        assert_eq!(decoded.blob.len(), blob.len());
+
+        let mut sink = Vec::new();
+        Config::new()
+            .from_reader_with_blob_sink::<_, _, IncomingStreamed>(
+                &bytes[..bytes.len() - 10],
+                &mut sink,
+            )
+            .unwrap_err();
+    }
+}
@@ -0,0 +1,319 @@
+use crate::de::Deserializer;
+use crate::read::{IoRead, Read};
+use crate::ser::{Counter, Serializer};
+use crate::value::Partial;
+use crate::{Error, Result, FORMAT_VERSION, HEADER, SIGNATURE_A, SIGNATURE_B};
+use byteorder::{LittleEndian, ReadBytesExt};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::io;
+
+/// Options that customize how values are serialized and deserialized.
+///
+/// The free functions like [`to_bytes`](crate::to_bytes) and [`from_bytes`](crate::from_bytes)
+/// behave like the corresponding methods of [`Config::default`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Config {
+    pub(crate) header: bool,
+    pub(crate) max_format_version: u8,
+    pub(crate) widen_f32: bool,
+    pub(crate) tuple_encoding: TupleEncoding,
+    pub(crate) char_encoding: CharEncoding,
+    pub(crate) strict: bool,
+    pub(crate) string_policy: StringPolicy,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            header: true,
+            max_format_version: FORMAT_VERSION,
+            widen_f32: false,
+            tuple_encoding: TupleEncoding::default(),
+            char_encoding: CharEncoding::default(),
+            strict: false,
+            string_policy: StringPolicy::default(),
+        }
+    }
+}
+
+/// How tuples, including fixed size arrays, are encoded.
+///
+/// Tuples of bytes are always encoded as a blob. Independent of this setting, tuples can be
+/// deserialized from all of the encodings.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TupleEncoding {
+    /// Encode tuples as an epee array.
+    ///
+    /// As epee arrays are typed, this only works for tuples whose elements share the same type.
+    #[default]
+    Array,
+    /// Encode tuples as a section whose fields are named after their position, i.e. `"0"`, `"1"`
+    /// and so on.
+    ///
+    /// This is the same encoding as used for tuple structs and works for tuples of any types.
+    Section,
+}
+
+/// How `char` is encoded.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CharEncoding {
+    /// Encode `char` as a string holding its UTF-8 representation.
+    ///
+    /// Deserialization fails unless the string holds exactly one character.
+    #[default]
+    Utf8,
+    /// Encode `char` as a single `u8`, which is how earlier versions of this crate encoded it.
+    ///
+    /// Serialization fails for characters beyond `U+00FF`.
+    Byte,
+}
+
+/// How epee strings are presented to types that accept any value, like untagged enums or
+/// `serde_json::Value`.
+///
+/// Epee strings hold arbitrary bytes, so by default they are presented as bytes. Types that expect
+/// a specific type, like `String` or `Vec<u8>`, are not affected by this setting.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum StringPolicy {
+    /// Always present strings as bytes.
+    #[default]
+    Bytes,
+    /// Present strings that are valid UTF-8 as text and all others as bytes.
+    Utf8ThenBytes,
+    /// Always present strings as text, replacing invalid UTF-8 sequences with `U+FFFD`.
+    Lossy,
+}
+
+impl Config {
+    /// Creates a configuration with the default options.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets whether the output starts with the magic header bytes and whether they are expected at
+    /// the beginning of the input.
+    ///
+    /// Without the header, only the bare root section is written and read. This is useful for
+    /// sections that are embedded in other data. Enabled by default.
+    pub fn header(mut self, header: bool) -> Self {
+        self.header = header;
+        self
+    }
+
+    /// Sets the highest format version in the header that is accepted when deserializing.
+    ///
+    /// Defaults to 1, the only version that exists so far, which is also the version that is
+    /// written when serializing. Raising it allows reading input of future versions as long as
+    /// their encoding of the values is compatible.
+    pub fn max_format_version(mut self, version: u8) -> Self {
+        self.max_format_version = version;
+        self
+    }
+
+    /// Allows `f32` values by storing them as epee's 64-bit floating point type.
+    ///
+    /// When deserializing, the 64-bit value is narrowed back to `f32`. Values that are out of the
+    /// range of `f32` or can't be represented exactly are rejected instead of being rounded.
+    ///
+    /// Disabled by default, in which case `f32` is rejected in both directions.
+    pub fn widen_f32(mut self, widen: bool) -> Self {
+        self.widen_f32 = widen;
+        self
+    }
+
+    /// Sets how tuples are encoded, defaults to [`TupleEncoding::Array`].
+    pub fn tuple_encoding(mut self, encoding: TupleEncoding) -> Self {
+        self.tuple_encoding = encoding;
+        self
+    }
+
+    /// Sets how `char` is encoded, defaults to [`CharEncoding::Utf8`].
+    pub fn char_encoding(mut self, encoding: CharEncoding) -> Self {
+        self.char_encoding = encoding;
+        self
+    }
+
+    /// Sets how epee strings are presented to types that accept any value, defaults to
+    /// [`StringPolicy::Bytes`].
+    pub fn string_policy(mut self, policy: StringPolicy) -> Self {
+        self.string_policy = policy;
+        self
+    }
+
+    /// Only accepts values whose epee type exactly matches the Rust type they are deserialized
+    /// into, e.g. a `u64` must be encoded as a `u64` and a `u32` is rejected.
+    ///
+    /// Disabled by default, in which case values of other types are accepted as long as they can
+    /// be converted without loss.
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    /// Serialize the given object to binary using this configuration.
+    ///
+    /// See [`to_bytes`](crate::to_bytes).
+    pub fn to_bytes<T>(&self, object: &T) -> Result<Vec<u8>>
+    where
+        T: Serialize,
+    {
+        let mut buffer = Vec::new();
+        if self.header {
+            buffer.extend_from_slice(HEADER);
+        }
+
+        let mut serializer = Serializer::new_root(&mut buffer, *self);
+        object.serialize(&mut serializer)?;
+
+        Ok(buffer)
+    }
+
+    /// Serialize the given object into the given writer using this configuration.
+    ///
+    /// See [`to_writer`](crate::to_writer).
+    pub fn to_writer<W, T>(&self, mut writer: W, object: &T) -> Result<()>
+    where
+        W: io::Write,
+        T: Serialize,
+    {
+        if self.header {
+            writer.write_all(HEADER)?;
+        }
+
+        let mut serializer = Serializer::new_root(&mut writer, *self);
+        object.serialize(&mut serializer)?;
+
+        Ok(())
+    }
+
+    /// Computes the length of the binary representation of the given object using this
+    /// configuration.
+    ///
+    /// See [`serialized_size`](crate::serialized_size).
+    pub fn serialized_size<T>(&self, object: &T) -> Result<usize>
+    where
+        T: Serialize,
+    {
+        let counter = Counter::default();
+        let mut writer = &counter;
+
+        let mut serializer = Serializer::new_counting(&mut writer, &counter, *self);
+        object.serialize(&mut serializer)?;
+
+        let mut count = counter.count();
+        if self.header {
+            count += HEADER.len();
+        }
+
+        Ok(count)
+    }
+
+    /// Deserialize the provided bytes using this configuration.
+    ///
+    /// See [`from_bytes`](crate::from_bytes).
+    pub fn from_bytes<T, B>(&self, bytes: B) -> Result<T>
+    where
+        T: DeserializeOwned,
+        B: AsRef<[u8]>,
+    {
+        self.from_slice(bytes.as_ref())
+    }
+
+    /// Deserialize an instance of `T` that may borrow from the provided bytes using this
+    /// configuration.
+    ///
+    /// See [`from_slice`](crate::from_slice).
+    pub fn from_slice<'de, T>(&self, mut bytes: &'de [u8]) -> Result<T>
+    where
+        T: Deserialize<'de>,
+    {
+        self.deserialize(&mut bytes, None)
+    }
+
+    /// Parses the provided bytes into a [`Section`](crate::value::Section), keeping everything
+    /// that could be parsed if the input is malformed.
+    ///
+    /// This is meant for analyzing malformed input, e.g. from misbehaving peers. Instead of
+    /// failing, all errors are returned together with their offset into the input, see
+    /// [`Partial`].
+    pub fn from_slice_lenient(&self, bytes: &[u8]) -> Partial {
+        crate::lenient::parse(bytes, *self)
+    }
+
+    /// Deserialize an instance of `T` from the given reader using this configuration.
+    ///
+    /// See [`from_reader`](crate::from_reader).
+    pub fn from_reader<R, T>(&self, reader: R) -> Result<T>
+    where
+        R: io::Read,
+        T: DeserializeOwned,
+    {
+        self.deserialize(&mut IoRead::new(io::BufReader::new(reader)), None)
+    }
+
+    /// Deserialize an instance of `T` from the given reader using this configuration, copying the
+    /// contents of all [`StreamedBlob`](crate::blob::StreamedBlob) fields into `sink`.
+    ///
+    /// The blobs are written one after the other in the order they appear in the input, their
+    /// lengths are available from the deserialized [`StreamedBlob`](crate::blob::StreamedBlob)s.
+    /// Apart from the buffer of the reader, the blobs are never held in memory.
+    pub fn from_reader_with_blob_sink<R, W, T>(&self, reader: R, mut sink: W) -> Result<T>
+    where
+        R: io::Read,
+        W: io::Write,
+        T: DeserializeOwned,
+    {
+        self.deserialize(
+            &mut IoRead::new(io::BufReader::new(reader)),
+            Some(&mut sink),
+        )
+    }
+
+    fn deserialize<'de, 'b, T>(
+        &self,
+        read: &'b mut dyn Read<'de>,
+        blob_sink: Option<&'b mut dyn io::Write>,
+    ) -> Result<T>
+    where
+        T: Deserialize<'de>,
+    {
+        if self.header {
+            self.read_header(read)?;
+        }
+
+        let mut deserializer = Deserializer::new(read, blob_sink, *self);
+
+        T::deserialize(&mut deserializer)
+    }
+
+    fn read_header(&self, read: &mut dyn Read<'_>) -> Result<()> {
+        fn missing_on_eof(e: io::Error) -> Error {
+            if e.kind() == io::ErrorKind::UnexpectedEof {
+                Error::missing_header_bytes()
+            } else {
+                e.into()
+            }
+        }
+
+        let signature_a = read.read_u32::<LittleEndian>().map_err(missing_on_eof)?;
+        if signature_a != SIGNATURE_A {
+            return Err(Error::invalid_signature_a(signature_a));
+        }
+
+        let signature_b = read.read_u32::<LittleEndian>().map_err(missing_on_eof)?;
+        if signature_b != SIGNATURE_B {
+            return Err(Error::invalid_signature_b(signature_b));
+        }
+
+        let version = read.read_u8().map_err(missing_on_eof)?;
+        if !(FORMAT_VERSION..=self.max_format_version).contains(&version) {
+            return Err(Error::unsupported_format_version(
+                version,
+                self.max_format_version,
+            ));
+        }
+
+        Ok(())
+    }
+}
@@ -1,5 +1,9 @@
-use serde::{de::Error as DeError, Deserialize, Deserializer, Serializer};
+use serde::{de::Error as DeError, Deserialize, Deserializer, Serialize, Serializer};
 use serde_bytes::ByteBuf;
+use std::fmt;
+use std::iter::FusedIterator;
+use std::marker::PhantomData;
+use std::slice::ChunksExact;
 
 pub trait ContainerAble {
     const SIZE: usize;
@@ -12,13 +16,28 @@ pub trait ContainerAble {
     fn push_bytes(&self, buf: &mut Vec<u8>);
 }
 
-pub fn deserialize<'de, D, T>(d: D) -> Result<Vec<T>, D::Error>
+/// Deserializes a blob into any container that can be built from an iterator of `T`.
+///
+/// This covers `Vec`, `VecDeque`, `LinkedList`, `HashSet`, `BTreeSet` and third-party
+/// containers like `SmallVec`. For fixed size arrays use [`array`](mod@array).
+pub fn deserialize<'de, D, C, T>(d: D) -> Result<C, D::Error>
 where
     D: Deserializer<'de>,
+    C: FromIterator<T> + IntoIterator<Item = T>,
     T: ContainerAble,
 {
     let bytes: ByteBuf = Deserialize::deserialize(d)?;
 
+    from_blob(&bytes)
+}
+
+/// Decodes the elements of a blob into a container.
+pub(crate) fn from_blob<C, T, E>(bytes: &[u8]) -> Result<C, E>
+where
+    C: FromIterator<T>,
+    T: ContainerAble,
+    E: DeError,
+{
     if bytes.len() % T::SIZE != 0 {
         return Err(DeError::invalid_length(
             bytes.len(),
@@ -26,25 +45,271 @@ where
         ));
     }
 
-    let ret = bytes
-        .windows(T::SIZE)
-        .step_by(T::SIZE)
-        .map(T::from_bytes)
-        .collect();
+    let ret = bytes.chunks_exact(T::SIZE).map(T::from_bytes).collect();
     Ok(ret)
 }
 
-pub fn serialize<S, T>(t: &[T], s: S) -> Result<S::Ok, S::Error>
+/// Serializes any container that can be iterated by reference as a single blob.
+pub fn serialize<S, C, T>(t: &C, s: S) -> Result<S::Ok, S::Error>
 where
     S: Serializer,
+    C: ?Sized,
+    for<'a> &'a C: IntoIterator<Item = &'a T>,
+    T: ContainerAble,
+{
+    s.serialize_bytes(&to_blob(t))
+}
+
+/// Encodes the elements of a container into a single blob.
+pub(crate) fn to_blob<C, T>(t: &C) -> Vec<u8>
+where
+    C: ?Sized,
+    for<'a> &'a C: IntoIterator<Item = &'a T>,
+    T: ContainerAble,
+{
+    let iter = t.into_iter();
+    let mut bytes = Vec::with_capacity(iter.size_hint().0 * T::SIZE);
+    iter.for_each(|tt| tt.push_bytes(&mut bytes));
+
+    bytes
+}
+
+/// `container_as_blob` for fixed size arrays, i.e. `[T; N]`.
+pub mod array {
+    use super::ContainerAble;
+    use serde::{de::Error as DeError, Deserialize, Deserializer, Serializer};
+    use serde_bytes::ByteBuf;
+
+    pub fn deserialize<'de, D, T, const N: usize>(d: D) -> Result<[T; N], D::Error>
+    where
+        D: Deserializer<'de>,
+        T: ContainerAble,
+    {
+        let bytes: ByteBuf = Deserialize::deserialize(d)?;
+
+        if bytes.len() != N * T::SIZE {
+            return Err(DeError::invalid_length(
+                bytes.len(),
+                &format!("{} bytes", N * T::SIZE).as_str(),
+            ));
+        }
+
+        let mut chunks = bytes.chunks_exact(T::SIZE);
+        Ok(std::array::from_fn(|_| {
+            T::from_bytes(chunks.next().expect("length checked above"))
+        }))
+    }
+
+    pub fn serialize<S, T, const N: usize>(t: &[T; N], s: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+        T: ContainerAble,
+    {
+        super::serialize(t, s)
+    }
+}
+
+/// `container_as_blob` for optional containers, i.e. `Option<C>`.
+///
+/// epee has no notion of a null value, an absent container is expressed by omitting the field.
+/// Combine this module with `#[serde(default, skip_serializing_if = "Option::is_none")]` so that
+/// `None` is skipped on serialization and a missing field is read back as `None`. A field that is
+/// present, even if its blob is empty, is deserialized as `Some`. Without `skip_serializing_if`,
+/// serializing `None` fails.
+///
+/// ```
+/// # use serde::{Deserialize, Serialize};
+/// #[derive(Deserialize, Serialize)]
+/// pub struct GetOutputDistributionResponse {
+///     #[serde(with = "monero_epee_bin_serde::container_as_blob::option")]
+///     #[serde(default, skip_serializing_if = "Option::is_none")]
+///     pub distribution: Option<Vec<u64>>,
+/// }
+/// ```
+pub mod option {
+    use super::ContainerAble;
+    use serde::{Deserializer, Serializer};
+
+    pub fn deserialize<'de, D, C, T>(d: D) -> Result<Option<C>, D::Error>
+    where
+        D: Deserializer<'de>,
+        C: FromIterator<T> + IntoIterator<Item = T>,
+        T: ContainerAble,
+    {
+        super::deserialize(d).map(Some)
+    }
+
+    pub fn serialize<S, C, T>(t: &Option<C>, s: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+        for<'a> &'a C: IntoIterator<Item = &'a T>,
+        T: ContainerAble,
+    {
+        match t {
+            Some(t) => super::serialize(t, s),
+            None => s.serialize_none(),
+        }
+    }
+}
+
+/// A zero-copy view of a blob written by [`serialize`].
+///
+/// Instead of collecting the elements into a container, the view borrows the blob from the input
+/// and decodes each `T` on the fly while iterating. It can therefore only be deserialized from a
+/// slice that outlives it, e.g. through [`from_slice`](crate::from_slice).
+pub struct BlobView<'de, T> {
+    bytes: &'de [u8],
+    _element: PhantomData<fn() -> T>,
+}
+
+impl<'de, T> BlobView<'de, T>
+where
+    T: ContainerAble,
+{
+    /// Returns the number of elements in the blob.
+    pub fn len(&self) -> usize {
+        self.bytes.len() / T::SIZE
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+
+    /// Returns the raw bytes of the blob.
+    pub fn as_bytes(&self) -> &'de [u8] {
+        self.bytes
+    }
+
+    /// Returns an iterator that decodes the elements of the blob.
+    pub fn iter(&self) -> BlobIter<'de, T> {
+        BlobIter {
+            chunks: self.bytes.chunks_exact(T::SIZE),
+            _element: PhantomData,
+        }
+    }
+}
+
+impl<'de, T> Clone for BlobView<'de, T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<'de, T> Copy for BlobView<'de, T> {}
+
+impl<'de, T> fmt::Debug for BlobView<'de, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BlobView")
+            .field("bytes", &self.bytes)
+            .finish()
+    }
+}
+
+impl<'de, T> PartialEq for BlobView<'de, T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.bytes == other.bytes
+    }
+}
+
+impl<'de, T> Eq for BlobView<'de, T> {}
+
+impl<'de, T> IntoIterator for BlobView<'de, T>
+where
     T: ContainerAble,
 {
-    let mut bytes = Vec::with_capacity(t.len() * T::SIZE);
-    t.iter().for_each(|tt| tt.push_bytes(&mut bytes));
+    type Item = T;
+    type IntoIter = BlobIter<'de, T>;
 
-    s.serialize_bytes(&bytes)
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'de, T> Serialize for BlobView<'de, T> {
+    fn serialize<S>(&self, s: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        s.serialize_bytes(self.bytes)
+    }
 }
 
+impl<'de: 'a, 'a, T> Deserialize<'de> for BlobView<'a, T>
+where
+    T: ContainerAble,
+{
+    fn deserialize<D>(d: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct Visitor<T>(PhantomData<fn() -> T>);
+
+        impl<'de, T> serde::de::Visitor<'de> for Visitor<T>
+        where
+            T: ContainerAble,
+        {
+            type Value = BlobView<'de, T>;
+
+            fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                write!(f, "a borrowed blob of {} byte elements", T::SIZE)
+            }
+
+            fn visit_borrowed_bytes<E>(self, bytes: &'de [u8]) -> Result<Self::Value, E>
+            where
+                E: DeError,
+            {
+                if bytes.len() % T::SIZE != 0 {
+                    return Err(DeError::invalid_length(
+                        bytes.len(),
+                        &"A number divisible by the fields size.",
+                    ));
+                }
+
+                Ok(BlobView {
+                    bytes,
+                    _element: PhantomData,
+                })
+            }
+        }
+
+        d.deserialize_bytes(Visitor(PhantomData))
+    }
+}
+
+/// Iterator over the elements of a [`BlobView`].
+pub struct BlobIter<'de, T> {
+    chunks: ChunksExact<'de, u8>,
+    _element: PhantomData<fn() -> T>,
+}
+
+impl<'de, T> Iterator for BlobIter<'de, T>
+where
+    T: ContainerAble,
+{
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        self.chunks.next().map(T::from_bytes)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.chunks.size_hint()
+    }
+}
+
+impl<'de, T> DoubleEndedIterator for BlobIter<'de, T>
+where
+    T: ContainerAble,
+{
+    fn next_back(&mut self) -> Option<T> {
+        self.chunks.next_back().map(T::from_bytes)
+    }
+}
+
+impl<'de, T> ExactSizeIterator for BlobIter<'de, T> where T: ContainerAble {}
+
+impl<'de, T> FusedIterator for BlobIter<'de, T> where T: ContainerAble {}
+
 impl<const N: usize> ContainerAble for [u8; N] {
     const SIZE: usize = N;
 
@@ -88,8 +353,10 @@ int_container_able!(i128);
 mod tests {
     use rand::random;
     use serde::{Deserialize, Serialize};
+    use std::collections::{BTreeSet, HashSet, LinkedList, VecDeque};
 
-    use crate::{container_as_blob, from_bytes, to_bytes};
+    use super::BlobView;
+    use crate::{container_as_blob, from_bytes, from_slice, to_bytes};
 
     #[test]
     fn ser_deser() {
@@ -147,4 +414,139 @@ mod tests {
 
         assert_eq!(t, tt);
     }
+
+    #[test]
+    fn ser_deser_optional() {
+        #[derive(Serialize, Deserialize, Debug, Eq, PartialEq)]
+        struct Test {
+            #[serde(with = "container_as_blob::option")]
+            #[serde(default, skip_serializing_if = "Option::is_none")]
+            hashes: Option<Vec<[u8; 32]>>,
+            #[serde(with = "container_as_blob::option")]
+            #[serde(default, skip_serializing_if = "Option::is_none")]
+            indices: Option<Vec<u64>>,
+        }
+
+        let cases = [
+            Test {
+                hashes: Some((0..10).map(|_| random()).collect()),
+                indices: None,
+            },
+            Test {
+                hashes: None,
+                indices: Some((0..10).map(|_| random()).collect()),
+            },
+            Test {
+                hashes: Some(Vec::new()),
+                indices: None,
+            },
+            Test {
+                hashes: None,
+                indices: None,
+            },
+        ];
+
+        for t in cases {
+            let bytes = to_bytes(&t).unwrap();
+
+            let tt = from_bytes(bytes).unwrap();
+
+            assert_eq!(t, tt);
+        }
+    }
+
+    #[test]
+    fn optional_none_requires_skip() {
+        #[derive(Serialize)]
+        struct Test {
+            #[serde(with = "container_as_blob::option")]
+            hashes: Option<Vec<[u8; 32]>>,
+        }
+
+        let err = to_bytes(&Test { hashes: None }).unwrap_err();
+        assert!(err.to_string().contains("skip_serializing_if"));
+
+        to_bytes(&Test {
+            hashes: Some(Vec::new()),
+        })
+        .unwrap();
+    }
+
+    #[test]
+    fn ser_deser_other_containers() {
+        #[derive(Serialize, Deserialize, Debug, Eq, PartialEq)]
+        struct Test {
+            #[serde(with = "container_as_blob")]
+            deque: VecDeque<u32>,
+            #[serde(with = "container_as_blob")]
+            list: LinkedList<i64>,
+            #[serde(with = "container_as_blob")]
+            key_images: HashSet<[u8; 32]>,
+            #[serde(with = "container_as_blob")]
+            ordered: BTreeSet<u16>,
+            #[serde(with = "container_as_blob::array")]
+            fixed: [u64; 3],
+        }
+
+        let t = Test {
+            deque: (0..10).map(|_| random()).collect(),
+            list: (0..10).map(|_| random()).collect(),
+            key_images: (0..10).map(|_| random()).collect(),
+            ordered: (0..10).map(|_| random()).collect(),
+            fixed: random(),
+        };
+
+        let bytes = to_bytes(&t).unwrap();
+
+        let tt = from_bytes(bytes).unwrap();
+
+        assert_eq!(t, tt);
+    }
+
+    #[test]
+    fn array_length_mismatch() {
+        #[derive(Serialize)]
+        struct Short {
+            #[serde(with = "container_as_blob")]
+            fixed: Vec<u64>,
+        }
+
+        #[derive(Deserialize, Debug)]
+        struct Long {
+            #[serde(with = "container_as_blob::array")]
+            #[allow(dead_code)]
+            fixed: [u64; 3],
+        }
+
+        let bytes = to_bytes(&Short { fixed: vec![1, 2] }).unwrap();
+
+        from_bytes::<Long, _>(bytes).unwrap_err();
+    }
+
+    #[test]
+    fn blob_view_borrows_from_input() {
+        #[derive(Serialize)]
+        struct Owned {
+            #[serde(with = "container_as_blob")]
+            o_indexes: Vec<u64>,
+        }
+
+        #[derive(Deserialize)]
+        struct Borrowed<'a> {
+            #[serde(borrow)]
+            o_indexes: BlobView<'a, u64>,
+        }
+
+        let o_indexes = (0..100).map(|_| random()).collect::<Vec<u64>>();
+        let bytes = to_bytes(&Owned {
+            o_indexes: o_indexes.clone(),
+        })
+        .unwrap();
+
+        let view = from_slice::<Borrowed>(&bytes).unwrap().o_indexes;
+
+        assert_eq!(view.len(), o_indexes.len());
+        assert!(bytes.as_ptr_range().contains(&view.as_bytes().as_ptr()));
+        assert_eq!(view.iter().collect::<Vec<_>>(), o_indexes);
+    }
 }
@@ -1,45 +1,127 @@
+use crate::read::{Read, Reference};
+use crate::value::{Section, Value, ValueDeserializer};
 use crate::{
-    varint, Error, Marker, Result, MARKER_SINGLE_BOOL, MARKER_SINGLE_F64, MARKER_SINGLE_I16,
-    MARKER_SINGLE_I32, MARKER_SINGLE_I64, MARKER_SINGLE_I8, MARKER_SINGLE_STRING,
-    MARKER_SINGLE_STRUCT, MARKER_SINGLE_U16, MARKER_SINGLE_U32, MARKER_SINGLE_U64,
-    MARKER_SINGLE_U8, MARKER_U8, MAX_STRING_LEN_POSSIBLE,
+    varint, CharEncoding, Config, Error, Marker, Result, StringPolicy, MARKER_ARRAY,
+    MARKER_SINGLE_BOOL, MARKER_SINGLE_F64, MARKER_SINGLE_I16, MARKER_SINGLE_I32, MARKER_SINGLE_I64,
+    MARKER_SINGLE_I8, MARKER_SINGLE_STRING, MARKER_SINGLE_STRUCT, MARKER_SINGLE_U16,
+    MARKER_SINGLE_U32, MARKER_SINGLE_U64, MARKER_SINGLE_U8, MARKER_U8, MAX_FIELD_NAME_LEN,
+    MAX_STRING_LEN_POSSIBLE, RECURSION_LIMIT,
 };
 use byteorder::{LittleEndian, ReadBytesExt};
-use serde::de::Visitor;
+use serde::de::value::{MapAccessDeserializer, SeqAccessDeserializer, SeqDeserializer};
+use serde::de::{IgnoredAny, IntoDeserializer, Visitor};
+use serde::Deserialize;
+use std::borrow::Cow;
 use std::io;
 
-pub struct Deserializer<'b> {
-    buffer: &'b mut dyn io::BufRead,
+pub struct Deserializer<'de, 'b> {
+    buffer: &'b mut dyn Read<'de>,
+    /// Where the contents of [`StreamedBlob`](crate::blob::StreamedBlob)s are copied to.
+    blob_sink: Option<&'b mut dyn io::Write>,
     read_header: bool,
+    /// The marker of the next value if it is not part of the input, i.e. for array elements.
+    pending_marker: Option<Marker>,
+    /// How many sections and arrays enclose the current value.
+    depth: usize,
+    /// The depth of the field whose value is captured into `captured` instead of being skipped
+    /// if the visitor ignores it, see [`WithUnknownFields`](crate::value::WithUnknownFields).
+    capture_depth: Option<usize>,
+    captured: Option<Value>,
+    config: Config,
 }
 
-impl<'b> Deserializer<'b> {
-    pub fn new(buffer: &'b mut dyn io::BufRead) -> Self {
+impl<'de, 'b> Deserializer<'de, 'b> {
+    pub fn new(
+        buffer: &'b mut dyn Read<'de>,
+        blob_sink: Option<&'b mut dyn io::Write>,
+        config: Config,
+    ) -> Self {
         Self {
             buffer,
+            blob_sink,
             read_header: false,
+            pending_marker: None,
+            depth: 0,
+            capture_depth: None,
+            captured: None,
+            config,
         }
     }
 }
 
-impl<'b> Deserializer<'b> {
+impl<'de, 'b> Deserializer<'de, 'b> {
     fn read_expected_marker(&mut self, expected_marker: Marker) -> Result<()> {
-        let actual_marker = self.read_marker()?;
+        let actual_marker = self.next_marker()?;
 
         if expected_marker != actual_marker {
-            return Err(io::Error::new(
-                io::ErrorKind::InvalidData,
-                format!(
-                    "expected marker {} but found {}",
-                    expected_marker, actual_marker
-                ),
-            )
-            .into());
+            return Err(Error::marker_mismatch(expected_marker, actual_marker));
         }
 
         Ok(())
     }
 
+    /// Deserializes a value of a type that corresponds to the given marker.
+    ///
+    /// In strict mode, the value must have exactly this marker. Otherwise, any value is passed to
+    /// the visitor, which accepts it if it can be converted, e.g. a `u32` for a `u64`.
+    fn deserialize_with_marker<V>(
+        &mut self,
+        expected_marker: Marker,
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        let marker = self.next_marker_in_strict_mode(expected_marker)?;
+
+        self.dispatch_based_on_marker(marker, visitor)
+    }
+
+    /// Returns the marker of the value that is about to be deserialized, which must be the
+    /// expected one in strict mode.
+    fn next_marker_in_strict_mode(&mut self, expected_marker: Marker) -> Result<Marker> {
+        let marker = self.next_marker()?;
+
+        if self.config.strict && expected_marker != marker {
+            return Err(Error::marker_mismatch(expected_marker, marker));
+        }
+
+        Ok(marker)
+    }
+
+    /// Returns the marker of the value that is about to be deserialized.
+    ///
+    /// The root section and array elements are not preceded by a marker, for those it is known
+    /// ahead of time.
+    fn next_marker(&mut self) -> Result<Marker> {
+        if let Some(marker) = self.pending_marker.take() {
+            return Ok(marker);
+        }
+
+        if !self.read_header {
+            self.read_header = true;
+            return Ok(MARKER_SINGLE_STRUCT);
+        }
+
+        self.read_marker()
+    }
+
+    /// Runs `f` for the contents of a section or an array, failing if they are nested too deeply.
+    fn nested<T, F>(&mut self, f: F) -> Result<T>
+    where
+        F: FnOnce(&mut Self) -> Result<T>,
+    {
+        if self.depth == RECURSION_LIMIT {
+            return Err(Error::recursion_limit_exceeded());
+        }
+
+        self.depth += 1;
+        let result = f(self);
+        self.depth -= 1;
+
+        result
+    }
+
     fn read_marker(&mut self) -> Result<Marker> {
         let marker_value = self.buffer.read_u8()?;
 
@@ -54,15 +136,37 @@ impl<'b> Deserializer<'b> {
         Ok(value)
     }
 
-    fn read_varint_bytes(&mut self) -> Result<Vec<u8>> {
+    fn read_varint_bytes(&mut self) -> Result<Reference<'de>> {
         let length = self.read_varint()?;
         if length > MAX_STRING_LEN_POSSIBLE {
             return Err(Error::length_exceeded_max_size());
         }
-        let buf = self.read_bytes(length)?;
+        let buf = self.buffer.read_reference(length)?;
         Ok(buf)
     }
 
+    /// Copies a blob to the blob sink, or skips it if there is none, and returns its length.
+    fn copy_blob_to_sink(&mut self) -> Result<u64> {
+        self.read_expected_marker(MARKER_SINGLE_STRING)?;
+
+        let length = self.read_varint()?;
+        if length > MAX_STRING_LEN_POSSIBLE {
+            return Err(Error::length_exceeded_max_size());
+        }
+
+        let mut blob = io::Read::take(&mut *self.buffer, length as u64);
+        let copied = match &mut self.blob_sink {
+            Some(sink) => io::copy(&mut blob, sink)?,
+            None => io::copy(&mut blob, &mut io::sink())?,
+        };
+
+        if copied != length as u64 {
+            return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+        }
+
+        Ok(copied)
+    }
+
     fn read_bool(&mut self) -> Result<bool> {
         let v = self.buffer.read_u8()?;
         let value = match v {
@@ -74,27 +178,56 @@ impl<'b> Deserializer<'b> {
         Ok(value)
     }
 
-    fn read_bytes(&mut self, length: usize) -> Result<Vec<u8>> {
-        let mut buffer = vec![0u8; length];
-        self.buffer.read_exact(&mut buffer)?;
-
-        Ok(buffer)
-    }
-
     fn read_varint(&mut self) -> Result<usize> {
         let varint = varint::decode(&mut self.buffer)?;
 
         Ok(varint)
     }
 
-    fn dispatch_based_on_marker<'de, V>(&mut self, marker: Marker, visitor: V) -> Result<V::Value>
+    /// Reads the contents of a string and presents them to the visitor according to the policy.
+    fn visit_string_contents<V>(&mut self, policy: StringPolicy, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        match (policy, self.read_varint_bytes()?) {
+            (StringPolicy::Bytes, Reference::Borrowed(bytes)) => {
+                visitor.visit_borrowed_bytes(bytes)
+            }
+            (StringPolicy::Bytes, Reference::Copied(bytes)) => visitor.visit_byte_buf(bytes),
+            (StringPolicy::Utf8ThenBytes, Reference::Borrowed(bytes)) => {
+                match std::str::from_utf8(bytes) {
+                    Ok(text) => visitor.visit_borrowed_str(text),
+                    Err(_) => visitor.visit_borrowed_bytes(bytes),
+                }
+            }
+            (StringPolicy::Utf8ThenBytes, Reference::Copied(bytes)) => {
+                match String::from_utf8(bytes) {
+                    Ok(text) => visitor.visit_string(text),
+                    Err(e) => visitor.visit_byte_buf(e.into_bytes()),
+                }
+            }
+            (StringPolicy::Lossy, Reference::Borrowed(bytes)) => {
+                match String::from_utf8_lossy(bytes) {
+                    Cow::Borrowed(text) => visitor.visit_borrowed_str(text),
+                    Cow::Owned(text) => visitor.visit_string(text),
+                }
+            }
+            (StringPolicy::Lossy, Reference::Copied(bytes)) => {
+                visitor.visit_string(String::from_utf8_lossy(&bytes).into_owned())
+            }
+        }
+    }
+
+    fn dispatch_based_on_marker<V>(&mut self, marker: Marker, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
         match marker {
             Marker::Sequence {
                 element: element_marker,
-            } => visitor.visit_seq(SeqAccess::with_varint_encoded_length(self, element_marker)?),
+            } => self.nested(|de| {
+                visitor.visit_seq(SeqAccess::with_varint_encoded_length(de, element_marker)?)
+            }),
             MARKER_SINGLE_I64 => visitor.visit_i64(self.buffer.read_i64::<LittleEndian>()?),
             MARKER_SINGLE_I32 => visitor.visit_i32(self.buffer.read_i32::<LittleEndian>()?),
             MARKER_SINGLE_I16 => visitor.visit_i16(self.buffer.read_i16::<LittleEndian>()?),
@@ -104,25 +237,27 @@ impl<'b> Deserializer<'b> {
             MARKER_SINGLE_U16 => visitor.visit_u16(self.buffer.read_u16::<LittleEndian>()?),
             MARKER_SINGLE_U8 => visitor.visit_u8(self.buffer.read_u8()?),
             MARKER_SINGLE_F64 => visitor.visit_f64(self.buffer.read_f64::<LittleEndian>()?),
-            MARKER_SINGLE_STRING => visitor.visit_byte_buf(self.read_varint_bytes()?),
+            MARKER_SINGLE_STRING => self.visit_string_contents(self.config.string_policy, visitor),
             MARKER_SINGLE_BOOL => visitor.visit_bool(self.read_bool()?),
-            MARKER_SINGLE_STRUCT => visitor.visit_map(MapAccess::with_varint_encoded_fields(self)?),
+            MARKER_SINGLE_STRUCT => {
+                self.nested(|de| visitor.visit_map(MapAccess::with_varint_encoded_fields(de)?))
+            }
             _ => Err(Error::unknown_marker(marker)),
         }
     }
 }
 
-pub struct MapAccess<'a, 'b> {
-    de: &'a mut Deserializer<'b>,
+pub struct MapAccess<'a, 'de, 'b> {
+    de: &'a mut Deserializer<'de, 'b>,
     number_of_fields: usize,
     fields_read: usize,
 }
 
-impl<'a, 'b> MapAccess<'a, 'b> {
+impl<'a, 'de, 'b> MapAccess<'a, 'de, 'b> {
     /// Creates a new instance of [`MapAccess`] that initializes itself by
     /// reading a varint from the reader within [`Deserializer`] for the
     /// expected number of fields.
-    fn with_varint_encoded_fields(de: &'a mut Deserializer<'b>) -> Result<Self> {
+    fn with_varint_encoded_fields(de: &'a mut Deserializer<'de, 'b>) -> Result<Self> {
         let number_of_fields = varint::decode(&mut de.buffer)?;
 
         Ok(MapAccess {
@@ -133,7 +268,7 @@ impl<'a, 'b> MapAccess<'a, 'b> {
     }
 }
 
-impl<'de, 'a, 'b> serde::de::MapAccess<'de> for MapAccess<'a, 'b> {
+impl<'de, 'a, 'b> serde::de::MapAccess<'de> for MapAccess<'a, 'de, 'b> {
     type Error = Error;
 
     fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>>
@@ -163,11 +298,11 @@ impl<'de, 'a, 'b> serde::de::MapAccess<'de> for MapAccess<'a, 'b> {
     }
 }
 
-struct SectionFieldNameDeserializer<'a, 'b> {
-    de: &'a mut Deserializer<'b>,
+struct SectionFieldNameDeserializer<'a, 'de, 'b> {
+    de: &'a mut Deserializer<'de, 'b>,
 }
 
-impl<'de, 'a, 'b> serde::de::Deserializer<'de> for SectionFieldNameDeserializer<'a, 'b> {
+impl<'de, 'a, 'b> serde::de::Deserializer<'de> for SectionFieldNameDeserializer<'a, 'de, 'b> {
     type Error = Error;
 
     fn deserialize_any<V>(self, visitor: V) -> Result<<V as Visitor<'de>>::Value>
@@ -175,6 +310,9 @@ impl<'de, 'a, 'b> serde::de::Deserializer<'de> for SectionFieldNameDeserializer<
         V: Visitor<'de>,
     {
         let field_name_length = self.de.buffer.read_u8()? as usize;
+        if field_name_length > MAX_FIELD_NAME_LEN {
+            return Err(Error::invalid_field_name_length(field_name_length));
+        }
         let field_name = self.de.read_string(field_name_length)?;
 
         visitor.visit_string(field_name)
@@ -185,10 +323,14 @@ impl<'de, 'a, 'b> serde::de::Deserializer<'de> for SectionFieldNameDeserializer<
         bytes byte_buf option unit unit_struct newtype_struct seq tuple
         tuple_struct map struct enum identifier ignored_any
     }
+
+    fn is_human_readable(&self) -> bool {
+        false
+    }
 }
 
-pub struct SeqAccess<'a, 'b> {
-    de: &'a mut Deserializer<'b>,
+pub struct SeqAccess<'a, 'de, 'b> {
+    de: &'a mut Deserializer<'de, 'b>,
     /// How long we expect the sequence to be.
     length: usize,
     /// What kind of item we are expecting.
@@ -197,9 +339,9 @@ pub struct SeqAccess<'a, 'b> {
     emitted_items: usize,
 }
 
-impl<'a, 'b> SeqAccess<'a, 'b> {
+impl<'a, 'de, 'b> SeqAccess<'a, 'de, 'b> {
     fn with_varint_encoded_length(
-        de: &'a mut Deserializer<'b>,
+        de: &'a mut Deserializer<'de, 'b>,
         element_marker: u8,
     ) -> Result<Self> {
         let length = de.read_varint()?;
@@ -207,7 +349,7 @@ impl<'a, 'b> SeqAccess<'a, 'b> {
         Ok(Self::with_length(de, element_marker, length))
     }
 
-    fn with_length(de: &'a mut Deserializer<'b>, element_marker: u8, length: usize) -> Self {
+    fn with_length(de: &'a mut Deserializer<'de, 'b>, element_marker: u8, length: usize) -> Self {
         Self {
             de,
             length,
@@ -217,7 +359,7 @@ impl<'a, 'b> SeqAccess<'a, 'b> {
     }
 }
 
-impl<'de, 'a, 'b> serde::de::SeqAccess<'de> for SeqAccess<'a, 'b> {
+impl<'de, 'a, 'b> serde::de::SeqAccess<'de> for SeqAccess<'a, 'de, 'b> {
     type Error = Error;
 
     fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>>
@@ -228,74 +370,366 @@ impl<'de, 'a, 'b> serde::de::SeqAccess<'de> for SeqAccess<'a, 'b> {
             return Ok(None);
         }
 
-        let element = seed.deserialize(SeqElementDeserializer {
-            de: self.de,
-            marker: self.element_marker,
-        })?;
+        // elements of arrays of arrays are preceded by their own marker
+        if self.element_marker != MARKER_ARRAY {
+            self.de.pending_marker = Some(Marker::Single {
+                value: self.element_marker,
+            });
+        }
+        let element = seed.deserialize(&mut *self.de);
+        // don't leak the marker to the next value if the element didn't read it
+        self.de.pending_marker = None;
+        let element = element?;
         self.emitted_items += 1;
 
         Ok(Some(element))
     }
 }
 
-struct SeqElementDeserializer<'a, 'b> {
-    de: &'a mut Deserializer<'b>,
-    marker: u8,
+/// Provides the fields of a section with positional field names, i.e. `"0"`, `"1"` and so on, as
+/// a sequence.
+struct PositionalAccess<'a, 'de, 'b> {
+    de: &'a mut Deserializer<'de, 'b>,
+    length: usize,
+    emitted_items: usize,
 }
 
-impl<'de, 'a, 'b> serde::de::Deserializer<'de> for SeqElementDeserializer<'a, 'b> {
+impl<'de, 'a, 'b> serde::de::SeqAccess<'de> for PositionalAccess<'a, 'de, 'b> {
     type Error = Error;
 
-    fn deserialize_any<V>(self, visitor: V) -> Result<<V as Visitor<'de>>::Value>
+    fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>>
     where
-        V: Visitor<'de>,
+        T: serde::de::DeserializeSeed<'de>,
     {
-        self.de
-            .dispatch_based_on_marker(Marker::Single { value: self.marker }, visitor)
+        if self.emitted_items == self.length {
+            return Ok(None);
+        }
+
+        let expected = self.emitted_items.to_string();
+        let field_name = String::deserialize(SectionFieldNameDeserializer { de: &mut *self.de })?;
+
+        if field_name != expected {
+            return Err(Error::unexpected_field_name(expected, field_name));
+        }
+
+        let element = seed.deserialize(&mut *self.de)?;
+        self.emitted_items += 1;
+
+        Ok(Some(element))
     }
 
-    serde::forward_to_deserialize_any! {
-        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
-        bytes byte_buf option unit unit_struct newtype_struct seq tuple
-        tuple_struct map struct enum identifier ignored_any
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.length - self.emitted_items)
     }
 }
 
-impl<'de, 'a, 'b> serde::Deserializer<'de> for &'a mut Deserializer<'b> {
+struct EnumAccess<'a, 'de, 'b> {
+    de: &'a mut Deserializer<'de, 'b>,
+}
+
+impl<'de, 'a, 'b> serde::de::EnumAccess<'de> for EnumAccess<'a, 'de, 'b> {
     type Error = Error;
+    type Variant = Self;
 
-    fn deserialize_any<V>(self, visitor: V) -> Result<<V as Visitor<'de>>::Value>
+    fn variant_seed<V>(self, seed: V) -> Result<(V::Value, Self::Variant)>
+    where
+        V: serde::de::DeserializeSeed<'de>,
+    {
+        let variant = seed.deserialize(SectionFieldNameDeserializer { de: &mut *self.de })?;
+
+        Ok((variant, self))
+    }
+}
+
+impl<'de, 'a, 'b> serde::de::VariantAccess<'de> for EnumAccess<'a, 'de, 'b> {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<()> {
+        serde::de::IgnoredAny::deserialize(self.de)?;
+
+        Ok(())
+    }
+
+    fn newtype_variant_seed<T>(self, seed: T) -> Result<T::Value>
+    where
+        T: serde::de::DeserializeSeed<'de>,
+    {
+        seed.deserialize(self.de)
+    }
+
+    fn tuple_variant<V>(self, len: usize, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
-        if !self.read_header {
-            self.read_header = true;
-            return visitor.visit_map(MapAccess::with_varint_encoded_fields(self)?);
+        serde::Deserializer::deserialize_tuple(self.de, len, visitor)
+    }
+
+    fn struct_variant<V>(self, fields: &'static [&'static str], visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        serde::Deserializer::deserialize_struct(self.de, "", fields, visitor)
+    }
+}
+
+/// Provides the fields of a section to a type like [`MapAccess`], capturing the values that the
+/// type ignores together with their names.
+struct CapturingMapAccess<'a, 'de, 'b> {
+    fields: MapAccess<'a, 'de, 'b>,
+    /// The name of the field whose value is deserialized next.
+    name: String,
+    unknown: Section,
+}
+
+impl<'de, 'a, 'b> serde::de::MapAccess<'de> for CapturingMapAccess<'a, 'de, 'b> {
+    type Error = Error;
+
+    fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>>
+    where
+        K: serde::de::DeserializeSeed<'de>,
+    {
+        match self.fields.next_key()? {
+            Some(name) => {
+                self.name = name;
+
+                seed.deserialize(self.name.as_str().into_deserializer())
+                    .map(Some)
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value>
+    where
+        V: serde::de::DeserializeSeed<'de>,
+    {
+        let outer = self.fields.de.capture_depth.replace(self.fields.de.depth);
+        let value = self.fields.next_value_seed(seed);
+        self.fields.de.capture_depth = outer;
+        let value = value?;
+
+        if let Some(captured) = self.fields.de.captured.take() {
+            self.unknown.push(std::mem::take(&mut self.name), captured);
         }
 
-        let marker = self.read_marker()?;
+        Ok(value)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        self.fields.size_hint()
+    }
+}
+
+/// Provides a section as the type that is deserialized from its fields, followed by the fields
+/// that the type ignored.
+struct UnknownFieldsAccess<'a, 'de, 'b> {
+    map: CapturingMapAccess<'a, 'de, 'b>,
+    emitted_items: usize,
+}
+
+impl<'de, 'a, 'b> serde::de::SeqAccess<'de> for UnknownFieldsAccess<'a, 'de, 'b> {
+    type Error = Error;
+
+    fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>>
+    where
+        T: serde::de::DeserializeSeed<'de>,
+    {
+        let element = match self.emitted_items {
+            0 => seed.deserialize(MapAccessDeserializer::new(&mut self.map))?,
+            1 => {
+                let unknown = std::mem::take(&mut self.map.unknown);
+
+                seed.deserialize(ValueDeserializer(Value::Section(unknown)))?
+            }
+            _ => return Ok(None),
+        };
+        self.emitted_items += 1;
+
+        Ok(Some(element))
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(2 - self.emitted_items)
+    }
+}
+
+/// Provides an array as a newtype variant whose index is the marker of its elements.
+struct TypedArrayAccess<'a, 'de, 'b> {
+    element: u8,
+    values: SeqAccess<'a, 'de, 'b>,
+}
+
+impl<'de, 'a, 'b> serde::de::EnumAccess<'de> for TypedArrayAccess<'a, 'de, 'b> {
+    type Error = Error;
+    type Variant = SeqAccess<'a, 'de, 'b>;
+
+    fn variant_seed<V>(self, seed: V) -> Result<(V::Value, Self::Variant)>
+    where
+        V: serde::de::DeserializeSeed<'de>,
+    {
+        let element =
+            seed.deserialize(IntoDeserializer::<Error>::into_deserializer(self.element))?;
+
+        Ok((element, self.values))
+    }
+}
+
+impl<'de, 'a, 'b> serde::de::VariantAccess<'de> for SeqAccess<'a, 'de, 'b> {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<()> {
+        IgnoredAny.visit_seq(self)?;
+
+        Ok(())
+    }
+
+    fn newtype_variant_seed<T>(self, seed: T) -> Result<T::Value>
+    where
+        T: serde::de::DeserializeSeed<'de>,
+    {
+        seed.deserialize(SeqAccessDeserializer::new(self))
+    }
+
+    fn tuple_variant<V>(self, _: usize, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_seq(self)
+    }
+
+    fn struct_variant<V>(self, _: &'static [&'static str], visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_seq(self)
+    }
+}
+
+/// Implements `deserialize_*` methods for types that correspond to a single marker.
+macro_rules! deserialize_with_marker {
+    ($($method:ident => $marker:expr,)*) => {
+        $(
+            fn $method<V>(self, visitor: V) -> Result<<V as Visitor<'de>>::Value>
+            where
+                V: Visitor<'de>,
+            {
+                self.deserialize_with_marker($marker, visitor)
+            }
+        )*
+    };
+}
+
+impl<'de, 'a, 'b> serde::Deserializer<'de> for &'a mut Deserializer<'de, 'b> {
+    type Error = Error;
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<<V as Visitor<'de>>::Value>
+    where
+        V: Visitor<'de>,
+    {
+        let marker = self.next_marker()?;
         self.dispatch_based_on_marker(marker, visitor)
     }
 
     serde::forward_to_deserialize_any! {
-        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f64
-        bytes byte_buf newtype_struct seq map struct
-        identifier ignored_any
+        identifier
+    }
+
+    fn deserialize_ignored_any<V>(self, visitor: V) -> Result<<V as Visitor<'de>>::Value>
+    where
+        V: Visitor<'de>,
+    {
+        // keep the value of an unknown field with its exact type instead of skipping it
+        if self.capture_depth == Some(self.depth) {
+            self.capture_depth = None;
+            self.captured = Some(Value::deserialize(&mut *self)?);
+
+            return visitor.visit_unit();
+        }
+
+        self.deserialize_any(visitor)
+    }
+
+    deserialize_with_marker! {
+        deserialize_map => MARKER_SINGLE_STRUCT,
+        deserialize_bool => MARKER_SINGLE_BOOL,
+        deserialize_i8 => MARKER_SINGLE_I8,
+        deserialize_i16 => MARKER_SINGLE_I16,
+        deserialize_i32 => MARKER_SINGLE_I32,
+        deserialize_i64 => MARKER_SINGLE_I64,
+        deserialize_u8 => MARKER_SINGLE_U8,
+        deserialize_u16 => MARKER_SINGLE_U16,
+        deserialize_u32 => MARKER_SINGLE_U32,
+        deserialize_u64 => MARKER_SINGLE_U64,
+        deserialize_f64 => MARKER_SINGLE_F64,
+    }
+
+    fn deserialize_bytes<V>(self, visitor: V) -> Result<<V as Visitor<'de>>::Value>
+    where
+        V: Visitor<'de>,
+    {
+        // independent of the policy, strings are always bytes to types that expect bytes
+        match self.next_marker_in_strict_mode(MARKER_SINGLE_STRING)? {
+            MARKER_SINGLE_STRING => self.visit_string_contents(StringPolicy::Bytes, visitor),
+            marker => self.dispatch_based_on_marker(marker, visitor),
+        }
+    }
+
+    fn deserialize_byte_buf<V>(self, visitor: V) -> Result<<V as Visitor<'de>>::Value>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_bytes(visitor)
+    }
+
+    fn deserialize_i128<V>(self, _: V) -> Result<<V as Visitor<'de>>::Value>
+    where
+        V: Visitor<'de>,
+    {
+        Err(Error::i128_is_not_supported())
     }
 
-    fn deserialize_f32<V>(self, _: V) -> Result<<V as Visitor<'de>>::Value>
+    fn deserialize_u128<V>(self, _: V) -> Result<<V as Visitor<'de>>::Value>
     where
         V: Visitor<'de>,
     {
-        Err(Error::f32_is_not_supported())
+        Err(Error::u128_is_not_supported())
+    }
+
+    fn deserialize_f32<V>(self, visitor: V) -> Result<<V as Visitor<'de>>::Value>
+    where
+        V: Visitor<'de>,
+    {
+        if !self.config.widen_f32 {
+            return Err(Error::f32_is_not_supported());
+        }
+
+        self.read_expected_marker(MARKER_SINGLE_F64)?;
+        let value = self.buffer.read_f64::<LittleEndian>()?;
+
+        visitor.visit_f32(narrow_to_f32(value)?)
     }
 
     fn deserialize_char<V>(self, visitor: V) -> Result<<V as Visitor<'de>>::Value>
     where
         V: Visitor<'de>,
     {
-        self.read_expected_marker(MARKER_SINGLE_U8)?;
-        visitor.visit_char(self.buffer.read_u8()? as char)
+        match self.config.char_encoding {
+            CharEncoding::Utf8 => {
+                self.read_expected_marker(MARKER_SINGLE_STRING)?;
+                let string = String::from_utf8(self.read_varint_bytes()?.into_owned())
+                    .map_err(Error::invalid_string)?;
+
+                let mut chars = string.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(c), None) => visitor.visit_char(c),
+                    _ => Err(Error::expected_single_char(string)),
+                }
+            }
+            CharEncoding::Byte => {
+                self.read_expected_marker(MARKER_SINGLE_U8)?;
+                visitor.visit_char(self.buffer.read_u8()? as char)
+            }
+        }
     }
 
     fn deserialize_str<V>(self, visitor: V) -> Result<V::Value>
@@ -310,8 +744,8 @@ impl<'de, 'a, 'b> serde::Deserializer<'de> for &'a mut Deserializer<'b> {
         V: Visitor<'de>,
     {
         self.read_expected_marker(MARKER_SINGLE_STRING)?;
-        let potential_str = self.read_varint_bytes()?;
-        visitor.visit_string(String::from_utf8(potential_str)?)
+        let potential_str = self.read_varint_bytes()?.into_owned();
+        visitor.visit_string(String::from_utf8(potential_str).map_err(Error::invalid_string)?)
     }
 
     fn deserialize_option<V>(self, visitor: V) -> Result<<V as Visitor<'de>>::Value>
@@ -322,18 +756,63 @@ impl<'de, 'a, 'b> serde::Deserializer<'de> for &'a mut Deserializer<'b> {
         visitor.visit_some(self)
     }
 
-    fn deserialize_unit<V>(self, _: V) -> Result<<V as Visitor<'de>>::Value>
+    fn deserialize_unit<V>(self, visitor: V) -> Result<<V as Visitor<'de>>::Value>
+    where
+        V: Visitor<'de>,
+    {
+        // unit is serialized as an empty section, fields added by newer versions are skipped
+        self.read_expected_marker(MARKER_SINGLE_STRUCT)?;
+        self.nested(|de| IgnoredAny.visit_map(MapAccess::with_varint_encoded_fields(de)?))?;
+
+        visitor.visit_unit()
+    }
+
+    fn deserialize_unit_struct<V>(
+        self,
+        _: &'static str,
+        visitor: V,
+    ) -> Result<<V as Visitor<'de>>::Value>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_unit(visitor)
+    }
+
+    fn deserialize_struct<V>(
+        self,
+        _: &'static str,
+        _: &'static [&'static str],
+        visitor: V,
+    ) -> Result<<V as Visitor<'de>>::Value>
     where
         V: Visitor<'de>,
     {
-        Err(Error::unit_is_not_supported())
+        self.deserialize_with_marker(MARKER_SINGLE_STRUCT, visitor)
     }
 
-    fn deserialize_unit_struct<V>(self, _: &'static str, _: V) -> Result<<V as Visitor<'de>>::Value>
+    fn deserialize_seq<V>(self, visitor: V) -> Result<<V as Visitor<'de>>::Value>
     where
         V: Visitor<'de>,
     {
-        Err(Error::unit_is_not_supported())
+        // byte sequences like `Vec<u8>` are serialized as "strings" in epee-bin, present them as a
+        // sequence of individual bytes to the visitor
+        match self.next_marker()? {
+            // in strict mode, only arrays of any type and blobs are sequences
+            marker @ Marker::Single { .. }
+                if self.config.strict && marker != MARKER_SINGLE_STRING =>
+            {
+                Err(Error::expected_array(marker))
+            }
+            MARKER_SINGLE_STRING => match self.read_varint_bytes()? {
+                Reference::Borrowed(bytes) => {
+                    visitor.visit_seq(SeqDeserializer::new(bytes.iter().copied().map(BlobByte)))
+                }
+                Reference::Copied(bytes) => {
+                    visitor.visit_seq(SeqDeserializer::new(bytes.into_iter().map(BlobByte)))
+                }
+            },
+            marker => self.dispatch_based_on_marker(marker, visitor),
+        }
     }
 
     fn deserialize_tuple<V>(
@@ -347,7 +826,8 @@ impl<'de, 'a, 'b> serde::Deserializer<'de> for &'a mut Deserializer<'b> {
         // special case tuples.
         // byte arrays and sequences are serialized as "strings" in epee-bin
         // hence, if we are told to deserialize a tuple, we check if the marker is a string, if that is the case, tell the deserializer to deserialize it as individual bytes
-        match self.read_marker()? {
+        // depending on the `TupleEncoding` all other tuples are either arrays or sections with positional field names
+        match self.next_marker()? {
             MARKER_SINGLE_STRING => {
                 let got_length = self.read_varint()?;
 
@@ -357,31 +837,237 @@ impl<'de, 'a, 'b> serde::Deserializer<'de> for &'a mut Deserializer<'b> {
 
                 v.visit_seq(SeqAccess::with_length(self, MARKER_U8, got_length))
             }
+            Marker::Sequence { element } => self.nested(|de| {
+                let seq = SeqAccess::with_varint_encoded_length(de, element)?;
+
+                if expected_length != seq.length {
+                    return Err(Error::length_mismatch(expected_length, seq.length));
+                }
+
+                v.visit_seq(seq)
+            }),
+            MARKER_SINGLE_STRUCT => self.nested(|de| {
+                let got_length = de.read_varint()?;
+
+                if expected_length != got_length {
+                    return Err(Error::length_mismatch(expected_length, got_length));
+                }
+
+                v.visit_seq(PositionalAccess {
+                    de,
+                    length: got_length,
+                    emitted_items: 0,
+                })
+            }),
             marker => Err(Error::tuples_of_type_are_not_supported(marker)),
         }
     }
 
+    fn deserialize_newtype_struct<V>(
+        self,
+        name: &'static str,
+        visitor: V,
+    ) -> Result<<V as Visitor<'de>>::Value>
+    where
+        V: Visitor<'de>,
+    {
+        if name == crate::blob::TOKEN {
+            let length = self.copy_blob_to_sink()?;
+
+            return visitor.visit_u64(length);
+        }
+
+        // pass the fields of a section that the type ignores to `WithUnknownFields`, which can't
+        // capture them with their type through `#[serde(flatten)]`
+        if name == crate::value::FIELDS_TOKEN {
+            self.read_expected_marker(MARKER_SINGLE_STRUCT)?;
+
+            return self.nested(|de| {
+                let fields = MapAccess::with_varint_encoded_fields(de)?;
+
+                visitor.visit_seq(UnknownFieldsAccess {
+                    map: CapturingMapAccess {
+                        fields,
+                        name: String::new(),
+                        unknown: Section::new(),
+                    },
+                    emitted_items: 0,
+                })
+            });
+        }
+
+        // pass the element type of arrays to `Value`, which can't tell it from the elements if
+        // there are none
+        if name == crate::value::TOKEN {
+            return match self.next_marker()? {
+                Marker::Sequence { element } => self.nested(|de| {
+                    let values = SeqAccess::with_varint_encoded_length(de, element)?;
+
+                    visitor.visit_enum(TypedArrayAccess { element, values })
+                }),
+                marker => self.dispatch_based_on_marker(marker, visitor),
+            };
+        }
+
+        visitor.visit_newtype_struct(self)
+    }
+
     fn deserialize_tuple_struct<V>(
         self,
         _: &'static str,
-        _: usize,
-        _: V,
+        expected_length: usize,
+        v: V,
     ) -> Result<<V as Visitor<'de>>::Value>
     where
         V: Visitor<'de>,
     {
-        Err(Error::tuple_structs_are_not_supported())
+        self.deserialize_tuple(expected_length, v)
     }
 
     fn deserialize_enum<V>(
         self,
         _: &'static str,
         _: &'static [&'static str],
-        _: V,
+        visitor: V,
+    ) -> Result<<V as Visitor<'de>>::Value>
+    where
+        V: Visitor<'de>,
+    {
+        // unit variants are encoded by their name, all others as a section with a single field
+        // that is named after the variant and holds its payload
+        match self.next_marker()? {
+            MARKER_SINGLE_STRING => {
+                let variant = String::from_utf8(self.read_varint_bytes()?.into_owned())
+                    .map_err(Error::invalid_string)?;
+
+                visitor.visit_enum(variant.into_deserializer())
+            }
+            MARKER_SINGLE_STRUCT => self.nested(|de| {
+                let number_of_fields = de.read_varint()?;
+
+                if number_of_fields != 1 {
+                    return Err(Error::length_mismatch(1, number_of_fields));
+                }
+
+                visitor.visit_enum(EnumAccess { de })
+            }),
+            marker => Err(Error::enums_of_type_are_not_supported(marker)),
+        }
+    }
+
+    fn is_human_readable(&self) -> bool {
+        false
+    }
+}
+
+/// A byte of a blob that is read as a sequence.
+///
+/// Only types that expect a byte can be deserialized from it, other elements would otherwise
+/// silently accept the bytes of e.g. an encoded `Vec<u16>` one by one.
+struct BlobByte(u8);
+
+impl<'de> IntoDeserializer<'de, Error> for BlobByte {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self {
+        self
+    }
+}
+
+/// Implements `deserialize_*` methods of [`BlobByte`] for types that are not bytes.
+macro_rules! reject_blob_byte {
+    ($($method:ident($($arg:ty),*) => $expected:expr,)*) => {
+        $(
+            fn $method<V>(self, $(_: $arg,)* _: V) -> Result<<V as Visitor<'de>>::Value>
+            where
+                V: Visitor<'de>,
+            {
+                Err(Error::marker_mismatch(
+                    $expected.to_sequence(),
+                    MARKER_SINGLE_STRING,
+                ))
+            }
+        )*
+    };
+}
+
+impl<'de> serde::Deserializer<'de> for BlobByte {
+    type Error = Error;
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<<V as Visitor<'de>>::Value>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_u8(self.0)
+    }
+
+    serde::forward_to_deserialize_any! {
+        u8 ignored_any
+    }
+
+    fn deserialize_option<V>(self, visitor: V) -> Result<<V as Visitor<'de>>::Value>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_newtype_struct<V>(
+        self,
+        _: &'static str,
+        visitor: V,
     ) -> Result<<V as Visitor<'de>>::Value>
     where
         V: Visitor<'de>,
     {
-        Err(Error::enums_are_not_supported())
+        visitor.visit_newtype_struct(self)
     }
+
+    reject_blob_byte! {
+        deserialize_bool() => MARKER_SINGLE_BOOL,
+        deserialize_i8() => MARKER_SINGLE_I8,
+        deserialize_i16() => MARKER_SINGLE_I16,
+        deserialize_i32() => MARKER_SINGLE_I32,
+        deserialize_i64() => MARKER_SINGLE_I64,
+        deserialize_i128() => MARKER_SINGLE_I64,
+        deserialize_u16() => MARKER_SINGLE_U16,
+        deserialize_u32() => MARKER_SINGLE_U32,
+        deserialize_u64() => MARKER_SINGLE_U64,
+        deserialize_u128() => MARKER_SINGLE_U64,
+        deserialize_f32() => MARKER_SINGLE_F64,
+        deserialize_f64() => MARKER_SINGLE_F64,
+        deserialize_char() => MARKER_SINGLE_STRING,
+        deserialize_str() => MARKER_SINGLE_STRING,
+        deserialize_string() => MARKER_SINGLE_STRING,
+        deserialize_bytes() => MARKER_SINGLE_STRING,
+        deserialize_byte_buf() => MARKER_SINGLE_STRING,
+        deserialize_identifier() => MARKER_SINGLE_STRING,
+        deserialize_seq() => MARKER_SINGLE_STRING,
+        deserialize_tuple(usize) => MARKER_SINGLE_STRING,
+        deserialize_tuple_struct(&'static str, usize) => MARKER_SINGLE_STRING,
+        deserialize_unit() => MARKER_SINGLE_STRUCT,
+        deserialize_unit_struct(&'static str) => MARKER_SINGLE_STRUCT,
+        deserialize_map() => MARKER_SINGLE_STRUCT,
+        deserialize_struct(&'static str, &'static [&'static str]) => MARKER_SINGLE_STRUCT,
+        deserialize_enum(&'static str, &'static [&'static str]) => MARKER_SINGLE_STRUCT,
+    }
+
+    fn is_human_readable(&self) -> bool {
+        false
+    }
+}
+
+/// Converts an `f64` to `f32`, failing if that would change the value.
+fn narrow_to_f32(value: f64) -> Result<f32> {
+    let narrowed = value as f32;
+
+    if value.is_finite() && narrowed.is_infinite() {
+        return Err(Error::f32_out_of_range(value));
+    }
+
+    if !value.is_nan() && f64::from(narrowed) != value {
+        return Err(Error::f32_precision_loss(value));
+    }
+
+    Ok(narrowed)
 }
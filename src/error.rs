@@ -1,4 +1,6 @@
-use crate::Marker;
+use crate::{
+    Marker, FORMAT_VERSION, MAX_FIELD_NAME_LEN, RECURSION_LIMIT, SIGNATURE_A, SIGNATURE_B,
+};
 use std::convert::From;
 use std::string::{FromUtf8Error, String};
 use std::{fmt, io};
@@ -19,17 +21,33 @@ enum Kind {
     LengthMismatch { expected: usize, found: usize },
     LengthTooLong,
     MissingHeaderBytes,
+    InvalidSignatureA { found: u32 },
+    InvalidSignatureB { found: u32 },
+    UnsupportedFormatVersion { version: u8, max: u8 },
     InvalidFieldName(FromUtf8Error),
+    InvalidString(FromUtf8Error),
+    InvalidFieldNameLength { length: usize },
     UnknownMarker { value: Marker },
     Io(io::Error),
     Custom(String),
     RootMustBeStruct { value: Marker },
     F32IsNotSupported,
+    F32OutOfRange { value: f64 },
+    F32PrecisionLoss { value: f64 },
     NoneCanNotBeSerialized,
-    UnitIsNotSupported,
-    EnumsAreNotSupported,
+    EnumsOfTypeAreNotSupported { marker: Marker },
     TuplesOfTypeAreNotSupported { marker: Marker },
-    TupleStructsAreNotSupported,
+    UnexpectedFieldName { expected: String, found: String },
+    CharOutOfRange { value: char },
+    ExpectedSingleChar { found: String },
+    I128IsNotSupported,
+    U128IsNotSupported,
+    ArrayElementMismatch { expected: Marker, found: Marker },
+    MarkerMismatch { expected: Marker, found: Marker },
+    ExpectedArray { found: Marker },
+    UntypedArray,
+    KeyMustBeAString { marker: Marker },
+    RecursionLimitExceeded,
 }
 
 impl serde::ser::Error for Error {
@@ -54,6 +72,7 @@ impl std::error::Error for Error {
     fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
         match &self.kind {
             Kind::InvalidFieldName(inner) => Some(inner),
+            Kind::InvalidString(inner) => Some(inner),
             Kind::Io(inner) => Some(inner),
             _ => None,
         }
@@ -73,6 +92,36 @@ impl Error {
         }
     }
 
+    pub(crate) fn invalid_signature_a(found: u32) -> Self {
+        Self {
+            kind: Kind::InvalidSignatureA { found },
+        }
+    }
+
+    pub(crate) fn invalid_signature_b(found: u32) -> Self {
+        Self {
+            kind: Kind::InvalidSignatureB { found },
+        }
+    }
+
+    pub(crate) fn unsupported_format_version(version: u8, max: u8) -> Self {
+        Self {
+            kind: Kind::UnsupportedFormatVersion { version, max },
+        }
+    }
+
+    pub(crate) fn invalid_field_name_length(length: usize) -> Self {
+        Self {
+            kind: Kind::InvalidFieldNameLength { length },
+        }
+    }
+
+    pub(crate) fn invalid_string(error: FromUtf8Error) -> Self {
+        Self {
+            kind: Kind::InvalidString(error),
+        }
+    }
+
     pub(crate) fn unexpected_bool(value: u8) -> Self {
         Self {
             kind: Kind::UnexpectedBool { value },
@@ -97,6 +146,18 @@ impl Error {
         }
     }
 
+    pub(crate) fn f32_out_of_range(value: f64) -> Self {
+        Self {
+            kind: Kind::F32OutOfRange { value },
+        }
+    }
+
+    pub(crate) fn f32_precision_loss(value: f64) -> Self {
+        Self {
+            kind: Kind::F32PrecisionLoss { value },
+        }
+    }
+
     pub(crate) fn no_length() -> Self {
         Self {
             kind: Kind::NoLength,
@@ -109,9 +170,9 @@ impl Error {
         }
     }
 
-    pub(crate) fn enums_are_not_supported() -> Error {
+    pub(crate) fn enums_of_type_are_not_supported(marker: Marker) -> Error {
         Self {
-            kind: Kind::EnumsAreNotSupported,
+            kind: Kind::EnumsOfTypeAreNotSupported { marker },
         }
     }
 
@@ -121,15 +182,69 @@ impl Error {
         }
     }
 
-    pub(crate) fn tuple_structs_are_not_supported() -> Error {
+    pub(crate) fn char_out_of_range(value: char) -> Error {
+        Self {
+            kind: Kind::CharOutOfRange { value },
+        }
+    }
+
+    pub(crate) fn expected_single_char(found: String) -> Error {
+        Self {
+            kind: Kind::ExpectedSingleChar { found },
+        }
+    }
+
+    pub(crate) fn unexpected_field_name(expected: String, found: String) -> Error {
+        Self {
+            kind: Kind::UnexpectedFieldName { expected, found },
+        }
+    }
+
+    pub(crate) fn i128_is_not_supported() -> Error {
         Self {
-            kind: Kind::TupleStructsAreNotSupported,
+            kind: Kind::I128IsNotSupported,
         }
     }
 
-    pub(crate) fn unit_is_not_supported() -> Error {
+    pub(crate) fn u128_is_not_supported() -> Error {
         Self {
-            kind: Kind::UnitIsNotSupported,
+            kind: Kind::U128IsNotSupported,
+        }
+    }
+
+    pub(crate) fn key_must_be_a_string(marker: Marker) -> Error {
+        Self {
+            kind: Kind::KeyMustBeAString { marker },
+        }
+    }
+
+    pub(crate) fn recursion_limit_exceeded() -> Error {
+        Self {
+            kind: Kind::RecursionLimitExceeded,
+        }
+    }
+
+    pub(crate) fn array_element_mismatch(expected: Marker, found: Marker) -> Error {
+        Self {
+            kind: Kind::ArrayElementMismatch { expected, found },
+        }
+    }
+
+    pub(crate) fn marker_mismatch(expected: Marker, found: Marker) -> Error {
+        Self {
+            kind: Kind::MarkerMismatch { expected, found },
+        }
+    }
+
+    pub(crate) fn expected_array(found: Marker) -> Error {
+        Self {
+            kind: Kind::ExpectedArray { found },
+        }
+    }
+
+    pub(crate) fn untyped_array() -> Error {
+        Self {
+            kind: Kind::UntypedArray,
         }
     }
 
@@ -152,7 +267,28 @@ impl fmt::Display for Error {
             Kind::NoLength => write!(f, "Length of seq/map must be known ahead of time"),
             Kind::UnexpectedBool { value } => write!(f, "{} is not a valid boolean value", value),
             Kind::MissingHeaderBytes => write!(f, "Missing magic header bytes"),
+            Kind::InvalidSignatureA { found } => write!(
+                f,
+                "Expected header signature A {:#010x} but found {:#010x}",
+                SIGNATURE_A, found
+            ),
+            Kind::InvalidSignatureB { found } => write!(
+                f,
+                "Expected header signature B {:#010x} but found {:#010x}",
+                SIGNATURE_B, found
+            ),
+            Kind::UnsupportedFormatVersion { version, max } => write!(
+                f,
+                "Format version {} is not supported, expected a version between {} and {}",
+                version, FORMAT_VERSION, max
+            ),
             Kind::InvalidFieldName(_) => write!(f, "Fieldname contained non-UTF-8 characters"),
+            Kind::InvalidString(_) => write!(f, "String contained non-UTF-8 characters"),
+            Kind::InvalidFieldNameLength { length } => write!(
+                f,
+                "Fieldname must be at most {} bytes long but is {} bytes long",
+                MAX_FIELD_NAME_LEN, length
+            ),
             Kind::UnknownMarker { value } => write!(f, "Unknown marker value {}", value),
             Kind::Io(_) => write!(f, "Failed to read from or write to buffer"),
             Kind::Custom(inner) => write!(f, "{}", inner),
@@ -160,19 +296,59 @@ impl fmt::Display for Error {
                 write!(f, "Root element must be a struct but got {}", value)
             }
             Kind::F32IsNotSupported => write!(f, "Type f32 is not supported"),
+            Kind::F32OutOfRange { value } => write!(f, "{} is out of the range of f32", value),
+            Kind::F32PrecisionLoss { value } => {
+                write!(f, "{} can not be represented as f32 without loss", value)
+            }
             Kind::NoneCanNotBeSerialized => write!(f, "Optional fields must be wrapped in #[serde(skip_serializing_if = \"Option::is_none\")]"),
-            Kind::UnitIsNotSupported => write!(f, "Unit type is not supported"),
-            Kind::EnumsAreNotSupported => write!(f, "Enums are not supported"),
+            Kind::EnumsOfTypeAreNotSupported { marker } => {
+                write!(f, "Enums of type {} are not supported", marker)
+            }
             Kind::TuplesOfTypeAreNotSupported { marker } => {
                 write!(f, "Tuples of type {} are not supported", marker)
             }
-            Kind::TupleStructsAreNotSupported => write!(f, "Tuple structs are not supported"),
+            Kind::CharOutOfRange { value } => {
+                write!(f, "{:?} does not fit into a single byte", value)
+            }
+            Kind::ExpectedSingleChar { found } => {
+                write!(f, "Expected a single character but found {:?}", found)
+            }
+            Kind::UnexpectedFieldName { expected, found } => write!(
+                f,
+                "Expected field {} but found {}",
+                expected, found
+            ),
             Kind::LengthMismatch { expected, found } => write!(
                 f,
                 "Length mismatch, expected {} elements but found {}",
                 expected, found
             ),
             Kind::LengthTooLong => write!(f, "Length of field exceeded maximum size"),
+            Kind::I128IsNotSupported => write!(f, "Type i128 is not supported"),
+            Kind::U128IsNotSupported => write!(f, "Type u128 is not supported, split it into two u64 fields with `u128_pair`"),
+            Kind::KeyMustBeAString { marker } => {
+                write!(f, "Map keys must be strings but got {}", marker)
+            }
+            Kind::MarkerMismatch { expected, found } => {
+                write!(f, "Expected marker {} but found {}", expected, found)
+            }
+            Kind::ExpectedArray { found } => {
+                write!(f, "Expected an array or a blob but found {}", found)
+            }
+            Kind::UntypedArray => write!(
+                f,
+                "Arrays must have an element type, capture unknown fields with `WithUnknownFields` to keep it"
+            ),
+            Kind::RecursionLimitExceeded => write!(
+                f,
+                "Sections and arrays must not be nested more than {} levels deep",
+                RECURSION_LIMIT
+            ),
+            Kind::ArrayElementMismatch { expected, found } => write!(
+                f,
+                "Array elements must have the same type, expected {} but got {}",
+                expected, found
+            ),
         }
     }
 }
@@ -0,0 +1,252 @@
+//! `#[serde(with = "hex_or_blob")]` to encode bytes as a hex string in human-readable formats and
+//! as a blob in epee.
+//!
+//! monerod's JSON RPC endpoints represent hashes and keys as hex strings, while its binary
+//! endpoints store them as raw blobs. Switching on
+//! [`is_human_readable`](serde::Serializer::is_human_readable) allows the same type to be used
+//! with both, e.g. with `serde_json` and this crate:
+//!
+//! ```
+//! # use serde::{Deserialize, Serialize};
+//! #[derive(Deserialize, Serialize)]
+//! pub struct GetBlockRequest {
+//!     #[serde(with = "monero_epee_bin_serde::hex_or_blob")]
+//!     pub hash: [u8; 32],
+//! }
+//! ```
+//!
+//! Any type that can be viewed as bytes and be built from a `Vec<u8>` is supported, like `[u8; N]`
+//! or `Vec<u8>`. With the `container_as_blob` feature, `container` does the same for the
+//! containers supported by `container_as_blob`.
+
+use serde::de::{Error as DeError, SeqAccess, Unexpected, Visitor};
+use serde::{Deserializer, Serializer};
+use std::fmt;
+
+const HEX_DIGITS: &[u8; 16] = b"0123456789abcdef";
+
+pub fn deserialize<'de, D, T>(d: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: TryFrom<Vec<u8>>,
+{
+    let bytes = deserialize_bytes(d)?;
+    let length = bytes.len();
+
+    T::try_from(bytes).map_err(|_| DeError::invalid_length(length, &"a blob of matching length"))
+}
+
+pub fn serialize<S, T>(value: &T, s: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+    T: AsRef<[u8]> + ?Sized,
+{
+    serialize_bytes(value.as_ref(), s)
+}
+
+/// `hex_or_blob` for the containers supported by [`container_as_blob`](crate::container_as_blob).
+///
+/// The elements are concatenated as in a blob before being encoded as hex.
+#[cfg(feature = "container_as_blob")]
+pub mod container {
+    use crate::container_as_blob::{self, ContainerAble};
+    use serde::{Deserializer, Serializer};
+
+    pub fn deserialize<'de, D, C, T>(d: D) -> Result<C, D::Error>
+    where
+        D: Deserializer<'de>,
+        C: FromIterator<T> + IntoIterator<Item = T>,
+        T: ContainerAble,
+    {
+        container_as_blob::from_blob(&super::deserialize_bytes(d)?)
+    }
+
+    pub fn serialize<S, C, T>(t: &C, s: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+        C: ?Sized,
+        for<'a> &'a C: IntoIterator<Item = &'a T>,
+        T: ContainerAble,
+    {
+        super::serialize_bytes(&container_as_blob::to_blob(t), s)
+    }
+}
+
+fn serialize_bytes<S>(bytes: &[u8], s: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    if !s.is_human_readable() {
+        return s.serialize_bytes(bytes);
+    }
+
+    let hex = bytes
+        .iter()
+        .flat_map(|b| {
+            [
+                HEX_DIGITS[(b >> 4) as usize],
+                HEX_DIGITS[(b & 0xf) as usize],
+            ]
+        })
+        .map(char::from)
+        .collect::<String>();
+
+    s.serialize_str(&hex)
+}
+
+fn deserialize_bytes<'de, D>(d: D) -> Result<Vec<u8>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    if d.is_human_readable() {
+        d.deserialize_str(HexVisitor)
+    } else {
+        d.deserialize_byte_buf(BlobVisitor)
+    }
+}
+
+struct HexVisitor;
+
+impl<'de> Visitor<'de> for HexVisitor {
+    type Value = Vec<u8>;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "a hex string")
+    }
+
+    fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
+    where
+        E: DeError,
+    {
+        fn digit(c: u8) -> Option<u8> {
+            (c as char).to_digit(16).map(|d| d as u8)
+        }
+
+        if v.len() % 2 != 0 {
+            return Err(DeError::invalid_length(v.len(), &self));
+        }
+
+        v.as_bytes()
+            .chunks_exact(2)
+            .map(|pair| match (digit(pair[0]), digit(pair[1])) {
+                (Some(high), Some(low)) => Ok(high << 4 | low),
+                _ => Err(DeError::invalid_value(Unexpected::Str(v), &self)),
+            })
+            .collect()
+    }
+}
+
+struct BlobVisitor;
+
+impl<'de> Visitor<'de> for BlobVisitor {
+    type Value = Vec<u8>;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "a blob")
+    }
+
+    fn visit_bytes<E>(self, v: &[u8]) -> Result<Self::Value, E> {
+        Ok(v.to_vec())
+    }
+
+    fn visit_byte_buf<E>(self, v: Vec<u8>) -> Result<Self::Value, E> {
+        Ok(v)
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        let mut bytes = Vec::with_capacity(seq.size_hint().unwrap_or(0).min(4096));
+        while let Some(b) = seq.next_element()? {
+            bytes.push(b);
+        }
+
+        Ok(bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{from_bytes, to_bytes};
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Debug, Deserialize, Serialize, PartialEq)]
+    struct Block {
+        #[serde(with = "crate::hex_or_blob")]
+        hash: [u8; 4],
+        #[serde(with = "crate::hex_or_blob")]
+        blob: Vec<u8>,
+    }
+
+    #[derive(Debug, Deserialize, Serialize, PartialEq)]
+    struct RawBlock {
+        hash: [u8; 4],
+        blob: Vec<u8>,
+    }
+
+    fn block() -> Block {
+        Block {
+            hash: [0xde, 0xad, 0xbe, 0xef],
+            blob: vec![0x00, 0x0f, 0xf0],
+        }
+    }
+
+    #[test]
+    fn json_is_hex() {
+        let json = serde_json::to_string(&block()).unwrap();
+        assert_eq!(json, r#"{"hash":"deadbeef","blob":"000ff0"}"#);
+
+        let decoded: Block = serde_json::from_str(&json).unwrap();
+        assert_eq!(decoded, block());
+
+        let decoded: Block = serde_json::from_str(r#"{"hash":"DEADBEEF","blob":""}"#).unwrap();
+        assert_eq!(decoded.hash, block().hash);
+    }
+
+    #[test]
+    fn epee_is_a_blob() {
+        let bytes = to_bytes(&block()).unwrap();
+        let raw = RawBlock {
+            hash: block().hash,
+            blob: block().blob,
+        };
+        assert_eq!(bytes, to_bytes(&raw).unwrap());
+
+        assert_eq!(from_bytes::<Block, _>(&bytes).unwrap(), block());
+    }
+
+    #[test]
+    fn invalid_hex_fails() {
+        let err = serde_json::from_str::<Block>(r#"{"hash":"deadbeefff","blob":""}"#).unwrap_err();
+        assert!(err.to_string().contains("invalid length 5"));
+
+        let err = serde_json::from_str::<Block>(r#"{"hash":"deadbee","blob":""}"#).unwrap_err();
+        assert!(err.to_string().contains("invalid length 7"));
+
+        let err = serde_json::from_str::<Block>(r#"{"hash":"deadbeeg","blob":""}"#).unwrap_err();
+        assert!(err.to_string().contains("invalid value"));
+    }
+
+    #[cfg(feature = "container_as_blob")]
+    #[test]
+    fn containers_are_hex_or_blob() {
+        #[derive(Debug, Deserialize, Serialize, PartialEq)]
+        struct Indices {
+            #[serde(with = "crate::hex_or_blob::container")]
+            indices: Vec<u16>,
+        }
+
+        let indices = Indices {
+            indices: vec![1, 0x0203],
+        };
+
+        let json = serde_json::to_string(&indices).unwrap();
+        assert_eq!(json, r#"{"indices":"01000302"}"#);
+        assert_eq!(serde_json::from_str::<Indices>(&json).unwrap(), indices);
+
+        let bytes = to_bytes(&indices).unwrap();
+        assert_eq!(&bytes[bytes.len() - 5..], b"\x10\x01\x00\x03\x02");
+        assert_eq!(from_bytes::<Indices, _>(&bytes).unwrap(), indices);
+    }
+}
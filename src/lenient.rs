@@ -0,0 +1,272 @@
+//! A parser for [`Config::from_slice_lenient`](crate::Config::from_slice_lenient) that keeps
+//! everything it could parse when it encounters an error.
+
+use crate::value::{ElementType, Partial, Section, Value};
+use crate::{
+    varint, Config, Error, Marker, FORMAT_VERSION, MARKER_ARRAY, MARKER_SINGLE_BOOL,
+    MARKER_SINGLE_F64, MARKER_SINGLE_I16, MARKER_SINGLE_I32, MARKER_SINGLE_I64, MARKER_SINGLE_I8,
+    MARKER_SINGLE_STRING, MARKER_SINGLE_STRUCT, MARKER_SINGLE_U16, MARKER_SINGLE_U32,
+    MARKER_SINGLE_U64, MARKER_SINGLE_U8, MAX_FIELD_NAME_LEN, MAX_STRING_LEN_POSSIBLE,
+    RECURSION_LIMIT, SIGNATURE_A, SIGNATURE_B,
+};
+use std::io;
+
+pub fn parse(input: &[u8], config: Config) -> Partial {
+    let mut parser = Parser {
+        input,
+        position: 0,
+        // the root section counts towards the recursion limit like it does in `from_bytes`
+        depth: 1,
+        errors: Vec::new(),
+    };
+
+    let mut section = Section::new();
+    let _ = parser
+        .header(config)
+        .and_then(|_| parser.section(&mut section));
+
+    Partial {
+        section,
+        errors: parser.errors,
+    }
+}
+
+/// Signals that parsing can't continue, the error has already been recorded.
+struct Stop;
+
+struct Parser<'a> {
+    input: &'a [u8],
+    position: usize,
+    depth: usize,
+    errors: Vec<(usize, Error)>,
+}
+
+impl<'a> Parser<'a> {
+    /// Records an error at the given offset that parsing can recover from.
+    fn record(&mut self, offset: usize, error: Error) {
+        self.errors.push((offset, error));
+    }
+
+    /// Records an error at the given offset that parsing can't recover from.
+    fn stop(&mut self, offset: usize, error: Error) -> Stop {
+        self.record(offset, error);
+
+        Stop
+    }
+
+    fn take(&mut self, length: usize) -> Result<&'a [u8], Stop> {
+        let rest = &self.input[self.position..];
+
+        if length > rest.len() {
+            let error = io::Error::from(io::ErrorKind::UnexpectedEof).into();
+            return Err(self.stop(self.position, error));
+        }
+
+        self.position += length;
+
+        Ok(&rest[..length])
+    }
+
+    fn take_array<const N: usize>(&mut self) -> Result<[u8; N], Stop> {
+        let mut array = [0; N];
+        array.copy_from_slice(self.take(N)?);
+
+        Ok(array)
+    }
+
+    fn varint(&mut self) -> Result<usize, Stop> {
+        let start = self.position;
+        let mut rest = &self.input[start..];
+
+        match varint::decode(&mut rest) {
+            Ok(value) => {
+                self.position = self.input.len() - rest.len();
+
+                Ok(value)
+            }
+            Err(e) => Err(self.stop(start, e.into())),
+        }
+    }
+
+    fn header(&mut self, config: Config) -> Result<(), Stop> {
+        if !config.header {
+            return Ok(());
+        }
+
+        if self.input.len() < crate::HEADER.len() {
+            return Err(self.stop(0, Error::missing_header_bytes()));
+        }
+
+        // continue after a mismatch, the rest of the input might still be epee
+        let signature_a = u32::from_le_bytes(self.take_array()?);
+        if signature_a != SIGNATURE_A {
+            self.record(0, Error::invalid_signature_a(signature_a));
+        }
+
+        let signature_b = u32::from_le_bytes(self.take_array()?);
+        if signature_b != SIGNATURE_B {
+            self.record(4, Error::invalid_signature_b(signature_b));
+        }
+
+        let [version] = self.take_array()?;
+        if !(FORMAT_VERSION..=config.max_format_version).contains(&version) {
+            let error = Error::unsupported_format_version(version, config.max_format_version);
+            self.record(8, error);
+        }
+
+        Ok(())
+    }
+
+    /// Parses the fields of a section into `section`, which holds all fields that were parsed
+    /// completely or partially if parsing stops.
+    fn section(&mut self, section: &mut Section) -> Result<(), Stop> {
+        let number_of_fields = self.varint()?;
+
+        for _ in 0..number_of_fields {
+            let start = self.position;
+            let [length] = self.take_array()?;
+            if length as usize > MAX_FIELD_NAME_LEN {
+                self.record(start, Error::invalid_field_name_length(length as usize));
+            }
+
+            let name = self.take(length as usize)?.to_vec();
+            let name = String::from_utf8(name).unwrap_or_else(|e| {
+                let name = String::from_utf8_lossy(e.as_bytes()).into_owned();
+                self.record(start + 1, e.into());
+
+                name
+            });
+
+            let marker_offset = self.position;
+            let marker = self.marker()?;
+            let (value, result) = self.value(marker, marker_offset);
+            if let Some(value) = value {
+                section.push(name, value);
+            }
+            result?;
+        }
+
+        Ok(())
+    }
+
+    fn marker(&mut self) -> Result<Marker, Stop> {
+        let [marker] = self.take_array()?;
+
+        Ok(Marker::from_byte(marker))
+    }
+
+    /// Parses a value with the given marker, returning the value if it was at least parsed
+    /// partially.
+    ///
+    /// For array elements, `marker_offset` is the offset of the marker of the array.
+    fn value(&mut self, marker: Marker, marker_offset: usize) -> (Option<Value>, Result<(), Stop>) {
+        let start = self.position;
+
+        let value = match marker {
+            MARKER_SINGLE_STRUCT | Marker::Sequence { .. } => {
+                return self.container(marker, marker_offset);
+            }
+            MARKER_SINGLE_I64 => self.take_array().map(|b| Value::I64(i64::from_le_bytes(b))),
+            MARKER_SINGLE_I32 => self.take_array().map(|b| Value::I32(i32::from_le_bytes(b))),
+            MARKER_SINGLE_I16 => self.take_array().map(|b| Value::I16(i16::from_le_bytes(b))),
+            MARKER_SINGLE_I8 => self.take_array().map(|b| Value::I8(i8::from_le_bytes(b))),
+            MARKER_SINGLE_U64 => self.take_array().map(|b| Value::U64(u64::from_le_bytes(b))),
+            MARKER_SINGLE_U32 => self.take_array().map(|b| Value::U32(u32::from_le_bytes(b))),
+            MARKER_SINGLE_U16 => self.take_array().map(|b| Value::U16(u16::from_le_bytes(b))),
+            MARKER_SINGLE_U8 => self.take_array().map(|[b]| Value::U8(b)),
+            MARKER_SINGLE_F64 => self.take_array().map(|b| Value::F64(f64::from_le_bytes(b))),
+            MARKER_SINGLE_BOOL => self.take_array().map(|[b]| {
+                if b > 1 {
+                    self.record(start, Error::unexpected_bool(b));
+                }
+
+                Value::Bool(b != 0)
+            }),
+            MARKER_SINGLE_STRING => self.string(),
+            _ => Err(self.stop(marker_offset, Error::unknown_marker(marker))),
+        };
+
+        match value {
+            Ok(value) => (Some(value), Ok(())),
+            Err(stop) => (None, Err(stop)),
+        }
+    }
+
+    fn string(&mut self) -> Result<Value, Stop> {
+        let start = self.position;
+        let length = self.varint()?;
+        if length > MAX_STRING_LEN_POSSIBLE {
+            return Err(self.stop(start, Error::length_exceeded_max_size()));
+        }
+
+        Ok(Value::String(self.take(length)?.to_vec()))
+    }
+
+    /// Parses a section or an array, which hold the values parsed until parsing stops.
+    fn container(
+        &mut self,
+        marker: Marker,
+        marker_offset: usize,
+    ) -> (Option<Value>, Result<(), Stop>) {
+        if self.depth == RECURSION_LIMIT {
+            let error = Error::recursion_limit_exceeded();
+            return (None, Err(self.stop(marker_offset, error)));
+        }
+
+        self.depth += 1;
+        let parsed = match marker {
+            Marker::Sequence { element } => match ElementType::from_marker(element) {
+                Some(ty) => {
+                    let mut values = Vec::new();
+                    let result = self.array(element, marker_offset, &mut values);
+
+                    (
+                        Some(Value::Array {
+                            element: ty,
+                            values,
+                        }),
+                        result,
+                    )
+                }
+                None => {
+                    let error = Error::unknown_marker(Marker::Single { value: element });
+                    (None, Err(self.stop(marker_offset, error)))
+                }
+            },
+            _ => {
+                let mut section = Section::new();
+                let result = self.section(&mut section);
+
+                (Some(Value::Section(section)), result)
+            }
+        };
+        self.depth -= 1;
+
+        parsed
+    }
+
+    fn array(
+        &mut self,
+        element: u8,
+        marker_offset: usize,
+        values: &mut Vec<Value>,
+    ) -> Result<(), Stop> {
+        let length = self.varint()?;
+
+        for _ in 0..length {
+            // elements of arrays of arrays are preceded by their own marker
+            let (marker, marker_offset) = if element == MARKER_ARRAY {
+                let offset = self.position;
+                (self.marker()?, offset)
+            } else {
+                (Marker::Single { value: element }, marker_offset)
+            };
+
+            let (value, result) = self.value(marker, marker_offset);
+            values.extend(value);
+            result?;
+        }
+
+        Ok(())
+    }
+}
@@ -6,70 +6,135 @@
 
 #![forbid(unsafe_code)]
 
+pub mod blob;
+mod config;
 #[cfg(feature = "container_as_blob")]
 pub mod container_as_blob;
 mod de;
 mod error;
+pub mod hex_or_blob;
+mod lenient;
+pub mod net;
+mod read;
+mod repr_enum;
 mod ser;
+pub mod u128_pair;
+pub mod value;
 mod varint;
 
+pub use crate::config::{CharEncoding, Config, StringPolicy, TupleEncoding};
 pub use crate::error::Error;
 
-use crate::de::Deserializer;
-use crate::ser::Serializer;
 use serde::de::DeserializeOwned;
-use serde::Serialize;
-use std::fmt;
-use std::io::Read;
+use serde::{Deserialize, Serialize};
+use std::{fmt, io};
+
+#[doc(hidden)]
+pub mod __private {
+    pub use serde;
+
+    pub fn unknown_discriminant<T, E>(value: T, name: &str) -> E
+    where
+        T: std::fmt::Display,
+        E: serde::de::Error,
+    {
+        E::custom(format_args!("unknown discriminant {} for {}", value, name))
+    }
+}
 
 /// A specialized [`Result`] type for serde operations.
 pub type Result<T> = std::result::Result<T, Error>;
 
 /// Header that needs to be at the beginning of every binary blob that follows
 /// this binary serialization format.
+///
+/// It consists of [`SIGNATURE_A`] and [`SIGNATURE_B`] as little endian `u32` followed by
+/// [`FORMAT_VERSION`].
 const HEADER: &[u8] = b"\x01\x11\x01\x01\x01\x01\x02\x01\x01";
+const SIGNATURE_A: u32 = 0x01011101;
+const SIGNATURE_B: u32 = 0x01020101;
+/// The version of the format that is written, `PORTABLE_STORAGE_FORMAT_VER` in epee.
+const FORMAT_VERSION: u8 = 1;
 /// The maximum length a byte array (marked as a string) can be.
 const MAX_STRING_LEN_POSSIBLE: usize = 2000000000;
+/// How deeply sections and arrays may be nested, like `EPEE_PORTABLE_STORAGE_RECURSION_LIMIT` in
+/// epee.
+const RECURSION_LIMIT: usize = 100;
+/// The maximum length of the name of a section field, which is prefixed by its length as a `u8`.
+///
+/// epee only writes names shorter than `u8::MAX`.
+const MAX_FIELD_NAME_LEN: usize = u8::MAX as usize - 1;
 
 /// Serialize the given object to binary.
 ///
-/// This function will prepend the magic header bytes to the serialized object.
+/// This function will prepend the magic header bytes to the serialized object, see
+/// [`Config::header`] to omit them.
 /// Additionally, the passed in object MUST be a struct. Monero's RPC interface assumes that the root element is a struct without tagging it as such.
 pub fn to_bytes<T>(object: &T) -> Result<Vec<u8>>
 where
     T: Serialize,
 {
-    let mut buffer = Vec::new();
-    buffer.extend_from_slice(HEADER);
+    Config::default().to_bytes(object)
+}
 
-    let mut serializer = Serializer::new_root(&mut buffer);
-    object.serialize(&mut serializer)?;
+/// Serialize the given object into the given writer.
+///
+/// Like [`to_bytes`] but writes the output, including the magic header bytes, to `writer` instead
+/// of allocating it.
+pub fn to_writer<W, T>(writer: W, object: &T) -> Result<()>
+where
+    W: io::Write,
+    T: Serialize,
+{
+    Config::default().to_writer(writer, object)
+}
 
-    Ok(buffer)
+/// Computes the length of the binary representation of the given object, including the magic
+/// header bytes.
+///
+/// This runs the same logic as [`to_bytes`] without allocating the output and therefore fails for
+/// the same objects.
+pub fn serialized_size<T>(object: &T) -> Result<usize>
+where
+    T: Serialize,
+{
+    Config::default().serialized_size(object)
 }
 
 /// Deserialize the provided bytes.
 ///
-/// This function assumes that the bytes are prepended with the magic header and will fail otherwise.
+/// This function assumes that the bytes are prepended with the magic header and will fail otherwise,
+/// see [`Config::header`] to read input without it.
 pub fn from_bytes<T, B>(bytes: B) -> Result<T>
 where
     T: DeserializeOwned,
     B: AsRef<[u8]>,
 {
-    let mut bytes = bytes.as_ref();
-
-    let mut header = [0u8; 9];
-    bytes.read_exact(&mut header)?;
-
-    let has_header = header == HEADER;
-
-    if !has_header {
-        return Err(Error::missing_header_bytes());
-    }
+    Config::default().from_bytes(bytes)
+}
 
-    let mut deserializer = Deserializer::new(&mut bytes);
+/// Deserialize an instance of `T` that may borrow from the provided bytes.
+///
+/// Like [`from_bytes`] but allows `T` to hold on to parts of the input, e.g. through
+/// [`serde_bytes::Bytes`](https://docs.rs/serde_bytes) or `container_as_blob::BlobView`, instead of
+/// copying them.
+pub fn from_slice<'de, T>(bytes: &'de [u8]) -> Result<T>
+where
+    T: Deserialize<'de>,
+{
+    Config::default().from_slice(bytes)
+}
 
-    T::deserialize(&mut deserializer)
+/// Deserialize an instance of `T` from the given reader.
+///
+/// Like [`from_bytes`] but reads the input from `reader`. The reader is buffered internally, so it
+/// may be read beyond the end of the serialized object.
+pub fn from_reader<R, T>(reader: R) -> Result<T>
+where
+    R: io::Read,
+    T: DeserializeOwned,
+{
+    Config::default().from_reader(reader)
 }
 
 const MARKER_SINGLE_I64: Marker = Marker::Single { value: 1 };
@@ -85,9 +150,14 @@ const MARKER_SINGLE_F64: Marker = Marker::Single { value: 9 };
 const MARKER_SINGLE_STRING: Marker = Marker::Single { value: 10 };
 const MARKER_SINGLE_BOOL: Marker = Marker::Single { value: 11 };
 const MARKER_SINGLE_STRUCT: Marker = Marker::Single { value: 12 };
+/// The element marker of arrays of arrays, whose elements are preceded by their own marker.
+const MARKER_ARRAY: u8 = 13;
+const MARKER_SINGLE_ARRAY: Marker = Marker::Single {
+    value: MARKER_ARRAY,
+};
 const MARKER_ARRAY_ELEMENT: u8 = 0x80;
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 enum Marker {
     Single { value: u8 },
     Sequence { element: u8 },
@@ -106,15 +176,15 @@ impl Marker {
         Self::Single { value }
     }
 
-    fn to_sequence(&self) -> Self {
-        match *self {
+    fn to_sequence(self) -> Self {
+        match self {
             Marker::Single { value } => Marker::Sequence { element: value },
             Marker::Sequence { element } => Marker::Sequence { element },
         }
     }
 
-    fn to_byte(&self) -> u8 {
-        match *self {
+    fn to_byte(self) -> u8 {
+        match self {
             Marker::Single { value } => value,
             Marker::Sequence { element } => element | MARKER_ARRAY_ELEMENT,
         }
@@ -134,17 +204,27 @@ impl fmt::Display for Marker {
 mod tests {
     use super::*;
 
-    #[derive(Serialize)]
+    #[derive(Debug, Serialize, Deserialize, PartialEq)]
     struct RootStruct {
         foo: u64,
         bars: Vec<Bar>,
     }
 
-    #[derive(Serialize)]
+    #[derive(Debug, Serialize, Deserialize, PartialEq)]
     struct Bar {
         number: u64,
     }
 
+    #[test]
+    fn header_consists_of_signatures_and_version() {
+        let mut header = Vec::new();
+        header.extend_from_slice(&SIGNATURE_A.to_le_bytes());
+        header.extend_from_slice(&SIGNATURE_B.to_le_bytes());
+        header.push(FORMAT_VERSION);
+
+        assert_eq!(header, HEADER);
+    }
+
     #[test]
     fn nested_struct_has_struct_marker() {
         let bytes = to_bytes(&RootStruct {
@@ -158,6 +238,38 @@ mod tests {
         assert_eq!(&payload, b"\x08\x03foo\x05\x64\x00\x00\x00\x00\x00\x00\x00\x04bars\x8c\x08\x04\x06number\x05\x01\x00\x00\x00\x00\x00\x00\x00\x04\x06number\x05\x02\x00\x00\x00\x00\x00\x00\x00")
     }
 
+    #[test]
+    fn serialized_size_matches_length_of_bytes() {
+        let root = RootStruct {
+            foo: 100,
+            bars: vec![Bar { number: 1 }, Bar { number: 2 }],
+        };
+
+        let bytes = to_bytes(&root).unwrap();
+
+        assert_eq!(serialized_size(&root).unwrap(), bytes.len());
+        serialized_size(&1u64).unwrap_err();
+    }
+
+    #[test]
+    fn writer_and_reader_round_trip() {
+        let root = RootStruct {
+            foo: 100,
+            bars: vec![Bar { number: 1 }, Bar { number: 2 }],
+        };
+
+        let mut buffer = Vec::new();
+        to_writer(&mut buffer, &root).unwrap();
+        assert_eq!(buffer, to_bytes(&root).unwrap());
+
+        let decoded = from_reader::<_, RootStruct>(buffer.as_slice()).unwrap();
+        assert_eq!(decoded, root);
+
+        for length in 0..buffer.len() {
+            from_reader::<_, RootStruct>(&buffer[..length]).unwrap_err();
+        }
+    }
+
     #[test]
     fn root_element_must_be_struct() {
         to_bytes(&1u64).unwrap_err();
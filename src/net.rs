@@ -0,0 +1,246 @@
+//! Helpers to (de)serialize network types the way monerod does.
+//!
+//! epee is not human-readable, so the `Serialize` implementations of e.g. `Ipv4Addr` and
+//! `SocketAddrV4` already produce a compact encoding. It doesn't match the one of monerod though,
+//! which stores an IPv4 address as a `u32` and the port as a `u16` in the fields `m_ip` and
+//! `m_port` of an `ipv4_network_address`:
+//!
+//! ```
+//! # use serde::{Deserialize, Serialize};
+//! # use std::net::{Ipv4Addr, SocketAddrV4};
+//! #[derive(Deserialize, Serialize)]
+//! pub struct PeerlistEntry {
+//!     #[serde(with = "monero_epee_bin_serde::net::ipv4_network_address")]
+//!     pub adr: SocketAddrV4,
+//!     pub id: u64,
+//! }
+//!
+//! #[derive(Deserialize, Serialize)]
+//! pub struct LocalIp {
+//!     #[serde(with = "monero_epee_bin_serde::net::ipv4")]
+//!     pub ip: Ipv4Addr,
+//! }
+//! ```
+//!
+//! Like [`u128_pair`](crate::u128_pair), [`ipv4_network_address`] can be flattened to read and
+//! write `m_ip` and `m_port` as fields of the enclosing section.
+
+use serde::de::{Error as DeError, IgnoredAny, MapAccess, Visitor};
+use serde::ser::SerializeStruct;
+use serde::Serializer;
+use std::fmt;
+use std::net::{Ipv4Addr, SocketAddrV4};
+
+/// An `Ipv4Addr` as a `u32` whose little endian bytes are the octets of the address.
+///
+/// monerod keeps the address in network byte order in memory, which epee writes as is.
+pub mod ipv4 {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+    use std::net::Ipv4Addr;
+
+    pub fn deserialize<'de, D>(d: D) -> Result<Ipv4Addr, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        u32::deserialize(d).map(super::ip_from_u32)
+    }
+
+    pub fn serialize<S>(value: &Ipv4Addr, s: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        super::ip_to_u32(value).serialize(s)
+    }
+}
+
+/// A `SocketAddrV4` as the fields `m_ip` and `m_port`.
+///
+/// The address is encoded like [`ipv4`], the port as a `u16`.
+pub mod ipv4_network_address {
+    use serde::{Deserializer, Serializer};
+    use std::net::SocketAddrV4;
+
+    pub fn deserialize<'de, D>(d: D) -> Result<SocketAddrV4, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        d.deserialize_struct("ipv4_network_address", super::FIELDS, super::AddressVisitor)
+    }
+
+    pub fn serialize<S>(value: &SocketAddrV4, s: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        super::serialize_address(value, s)
+    }
+}
+
+/// A `Uuid` like the `network_id` of monerod, i.e. a blob of its 16 bytes.
+#[cfg(feature = "uuid")]
+pub mod network_id {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+    use uuid::Uuid;
+
+    pub fn deserialize<'de, D>(d: D) -> Result<Uuid, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        <[u8; 16]>::deserialize(d).map(Uuid::from_bytes)
+    }
+
+    pub fn serialize<S>(value: &Uuid, s: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        value.as_bytes().serialize(s)
+    }
+}
+
+const FIELDS: &[&str] = &["m_ip", "m_port"];
+
+fn ip_from_u32(ip: u32) -> Ipv4Addr {
+    Ipv4Addr::from(ip.to_le_bytes())
+}
+
+fn ip_to_u32(ip: &Ipv4Addr) -> u32 {
+    u32::from_le_bytes(ip.octets())
+}
+
+fn serialize_address<S>(value: &SocketAddrV4, s: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    let mut address = s.serialize_struct("ipv4_network_address", 2)?;
+    address.serialize_field(FIELDS[0], &ip_to_u32(value.ip()))?;
+    address.serialize_field(FIELDS[1], &value.port())?;
+    address.end()
+}
+
+struct AddressVisitor;
+
+impl<'de> Visitor<'de> for AddressVisitor {
+    type Value = SocketAddrV4;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "the fields {} and {}", FIELDS[0], FIELDS[1])
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        let mut ip = None;
+        let mut port = None;
+
+        while let Some(key) = map.next_key::<String>()? {
+            if key == FIELDS[0] {
+                ip = Some(map.next_value::<u32>()?);
+            } else if key == FIELDS[1] {
+                port = Some(map.next_value::<u16>()?);
+            } else {
+                map.next_value::<IgnoredAny>()?;
+            }
+        }
+
+        let ip = ip.ok_or_else(|| DeError::missing_field(FIELDS[0]))?;
+        let port = port.ok_or_else(|| DeError::missing_field(FIELDS[1]))?;
+
+        Ok(SocketAddrV4::new(ip_from_u32(ip), port))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{from_bytes, to_bytes};
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Debug, Deserialize, Serialize, PartialEq)]
+    struct Peer {
+        #[serde(with = "ipv4_network_address")]
+        adr: SocketAddrV4,
+        #[serde(with = "ipv4")]
+        ip: Ipv4Addr,
+    }
+
+    #[derive(Debug, Deserialize, Serialize, PartialEq)]
+    struct Flattened {
+        #[serde(flatten, with = "ipv4_network_address")]
+        adr: SocketAddrV4,
+        id: u64,
+    }
+
+    #[derive(Debug, Deserialize, Serialize, PartialEq)]
+    struct Raw {
+        m_ip: u32,
+        m_port: u16,
+    }
+
+    #[derive(Debug, Deserialize, Serialize, PartialEq)]
+    struct RawPeer {
+        adr: Raw,
+        ip: u32,
+    }
+
+    #[test]
+    fn ipv4_is_encoded_like_monerod() {
+        let peer = Peer {
+            adr: SocketAddrV4::new(Ipv4Addr::new(192, 168, 1, 2), 18080),
+            ip: Ipv4Addr::new(127, 0, 0, 1),
+        };
+        let bytes = to_bytes(&peer).unwrap();
+
+        let raw: RawPeer = from_bytes(&bytes).unwrap();
+        assert_eq!(
+            raw,
+            RawPeer {
+                adr: Raw {
+                    m_ip: 0x0201a8c0,
+                    m_port: 18080,
+                },
+                ip: 0x0100007f,
+            }
+        );
+        assert_eq!(from_bytes::<Peer, _>(&bytes).unwrap(), peer);
+    }
+
+    #[test]
+    fn flattened_address_is_part_of_the_section() {
+        let flattened = Flattened {
+            adr: SocketAddrV4::new(Ipv4Addr::new(10, 0, 0, 1), 28080),
+            id: 7,
+        };
+        let bytes = to_bytes(&flattened).unwrap();
+
+        assert_eq!(from_bytes::<Flattened, _>(&bytes).unwrap(), flattened);
+    }
+
+    #[cfg(feature = "uuid")]
+    #[test]
+    fn network_id_is_a_blob() {
+        #[derive(Debug, Deserialize, Serialize, PartialEq)]
+        struct NodeData {
+            #[serde(with = "network_id")]
+            network_id: uuid::Uuid,
+        }
+
+        #[derive(Debug, Deserialize, Serialize, PartialEq)]
+        struct RawNodeData {
+            network_id: [u8; 16],
+        }
+
+        let bytes: [u8; 16] = [
+            18, 48, 241, 113, 97, 4, 65, 97, 23, 49, 0, 130, 22, 161, 161, 16,
+        ];
+        let node_data = NodeData {
+            network_id: uuid::Uuid::from_bytes(bytes),
+        };
+
+        let encoded = to_bytes(&node_data).unwrap();
+        assert_eq!(
+            encoded,
+            to_bytes(&RawNodeData { network_id: bytes }).unwrap()
+        );
+        assert_eq!(from_bytes::<NodeData, _>(&encoded).unwrap(), node_data);
+    }
+}
@@ -0,0 +1,94 @@
+use std::io;
+use std::io::Read as _;
+
+/// A source of bytes for the [`Deserializer`](crate::de::Deserializer).
+///
+/// On top of [`io::BufRead`] this allows sources that hold the entire input in memory to hand out
+/// slices of it instead of copying them.
+pub trait Read<'de>: io::BufRead {
+    /// Reads exactly `length` bytes, borrowing them from the input if possible.
+    fn read_reference(&mut self, length: usize) -> io::Result<Reference<'de>>;
+}
+
+pub enum Reference<'de> {
+    Borrowed(&'de [u8]),
+    Copied(Vec<u8>),
+}
+
+impl<'de> Reference<'de> {
+    pub fn into_owned(self) -> Vec<u8> {
+        match self {
+            Reference::Borrowed(bytes) => bytes.to_vec(),
+            Reference::Copied(bytes) => bytes,
+        }
+    }
+}
+
+impl<'de> Read<'de> for &'de [u8] {
+    fn read_reference(&mut self, length: usize) -> io::Result<Reference<'de>> {
+        if length > self.len() {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+
+        let (bytes, rest) = self.split_at(length);
+        *self = rest;
+
+        Ok(Reference::Borrowed(bytes))
+    }
+}
+
+/// A [`Read`] implementation for sources that don't hold the entire input in memory.
+///
+/// Everything that is read is copied out of the underlying reader.
+pub struct IoRead<R> {
+    reader: R,
+}
+
+impl<R> IoRead<R>
+where
+    R: io::BufRead,
+{
+    pub fn new(reader: R) -> Self {
+        Self { reader }
+    }
+}
+
+impl<R> io::Read for IoRead<R>
+where
+    R: io::BufRead,
+{
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.reader.read(buf)
+    }
+}
+
+impl<R> io::BufRead for IoRead<R>
+where
+    R: io::BufRead,
+{
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        self.reader.fill_buf()
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.reader.consume(amt)
+    }
+}
+
+impl<'de, R> Read<'de> for IoRead<R>
+where
+    R: io::BufRead,
+{
+    fn read_reference(&mut self, length: usize) -> io::Result<Reference<'de>> {
+        // The length comes from the input, so grow the buffer while reading instead of trusting it
+        // upfront.
+        let mut bytes = Vec::new();
+        io::Read::take(&mut self.reader, length as u64).read_to_end(&mut bytes)?;
+
+        if bytes.len() != length {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+
+        Ok(Reference::Copied(bytes))
+    }
+}
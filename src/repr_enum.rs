@@ -0,0 +1,93 @@
+/// Defines a C-like enum that is encoded as the integer of its `repr`.
+///
+/// Monero uses plain integers for many enumerations, e.g. the `support_flags` of a node. Enums
+/// declared through this macro are serialized with the integer marker matching their `repr` and
+/// deserialization fails for discriminants that don't belong to a variant. The macro also
+/// implements `From<Enum>` for the `repr` type and `TryFrom<repr>` for the enum.
+///
+/// ```
+/// monero_epee_bin_serde::repr_enum! {
+///     #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+///     pub enum RelayMethod: u8 {
+///         None = 0,
+///         Local = 1,
+///         Forward = 2,
+///         Stem = 3,
+///         Fluff = 4,
+///         Block = 5,
+///     }
+/// }
+///
+/// assert_eq!(u8::from(RelayMethod::Stem), 3);
+/// assert_eq!(RelayMethod::try_from(4), Ok(RelayMethod::Fluff));
+/// assert!(RelayMethod::try_from(6).is_err());
+/// ```
+#[macro_export]
+macro_rules! repr_enum {
+    (
+        $(#[$meta:meta])*
+        $vis:vis enum $name:ident: $repr:ident {
+            $(
+                $(#[$variant_meta:meta])*
+                $variant:ident = $value:expr
+            ),* $(,)?
+        }
+    ) => {
+        $(#[$meta])*
+        #[repr($repr)]
+        $vis enum $name {
+            $(
+                $(#[$variant_meta])*
+                $variant = $value,
+            )*
+        }
+
+        impl ::core::convert::From<$name> for $repr {
+            fn from(value: $name) -> Self {
+                match value {
+                    $($name::$variant => $name::$variant as $repr,)*
+                }
+            }
+        }
+
+        impl ::core::convert::TryFrom<$repr> for $name {
+            type Error = $repr;
+
+            fn try_from(value: $repr) -> ::core::result::Result<Self, $repr> {
+                $(
+                    if value == $name::$variant as $repr {
+                        return ::core::result::Result::Ok($name::$variant);
+                    }
+                )*
+
+                ::core::result::Result::Err(value)
+            }
+        }
+
+        impl $crate::__private::serde::Serialize for $name {
+            fn serialize<S>(&self, serializer: S) -> ::core::result::Result<S::Ok, S::Error>
+            where
+                S: $crate::__private::serde::Serializer,
+            {
+                let value = match self {
+                    $($name::$variant => $name::$variant as $repr,)*
+                };
+
+                $crate::__private::serde::Serialize::serialize(&value, serializer)
+            }
+        }
+
+        impl<'de> $crate::__private::serde::Deserialize<'de> for $name {
+            fn deserialize<D>(deserializer: D) -> ::core::result::Result<Self, D::Error>
+            where
+                D: $crate::__private::serde::Deserializer<'de>,
+            {
+                let value: $repr = $crate::__private::serde::Deserialize::deserialize(deserializer)?;
+
+                ::core::convert::TryFrom::try_from(value).map_err(|value| {
+                    $crate::__private::unknown_discriminant(value, ::core::stringify!($name))
+                })
+            }
+        }
+    };
+}
@@ -1,35 +1,104 @@
+use crate::value::ElementType;
 use crate::{
-    Error, Marker, Result, MARKER_SINGLE_BOOL, MARKER_SINGLE_F64, MARKER_SINGLE_I16,
-    MARKER_SINGLE_I32, MARKER_SINGLE_I64, MARKER_SINGLE_I8, MARKER_SINGLE_STRING,
-    MARKER_SINGLE_STRUCT, MARKER_SINGLE_U16, MARKER_SINGLE_U32, MARKER_SINGLE_U64,
-    MARKER_SINGLE_U8,
+    CharEncoding, Config, Error, Marker, Result, TupleEncoding, MARKER_SINGLE_ARRAY,
+    MARKER_SINGLE_BOOL, MARKER_SINGLE_F64, MARKER_SINGLE_I16, MARKER_SINGLE_I32, MARKER_SINGLE_I64,
+    MARKER_SINGLE_I8, MARKER_SINGLE_STRING, MARKER_SINGLE_STRUCT, MARKER_SINGLE_U16,
+    MARKER_SINGLE_U32, MARKER_SINGLE_U64, MARKER_SINGLE_U8, MAX_FIELD_NAME_LEN, RECURSION_LIMIT,
 };
 use serde::Serialize;
+use std::cell::Cell;
 use std::io;
 
+/// The marker of sequences without elements, whose type is unknown.
+const MARKER_EMPTY_SEQUENCE: Marker = Marker::Sequence { element: 255 };
+
 pub struct Serializer<'b> {
     buffer: &'b mut dyn io::Write,
     state: State,
     is_root: bool,
+    /// The number of bytes that are still expected while streaming a blob, in which case bytes
+    /// are written as is.
+    streamed_blob: Option<usize>,
+    /// The marker of the elements of the next sequence if it is known ahead of time, i.e. for a
+    /// [`Value::Array`](crate::value::Value::Array).
+    array_element: Option<Marker>,
+    /// Set while computing the serialized size, in which case the contents of blobs that are
+    /// streamed from a source are counted instead of being read.
+    counter: Option<&'b Counter>,
+    /// How many sections and arrays enclose the current value, including ones that are only
+    /// written if they turn out not to be blobs.
+    depth: usize,
+    config: Config,
 }
 
 #[derive(Clone, Eq, PartialEq)]
 pub enum State {
     Empty,
-    First { length: usize },
-    Rest,
+    First {
+        length: usize,
+        /// The number of empty sequences that were written before it was known whether they are
+        /// blobs or arrays.
+        deferred: usize,
+    },
+    /// All elements of an array need to have the same marker as the first one.
+    Rest {
+        marker: Marker,
+        /// For arrays of arrays, the marker of the first array in it that is not empty, which the
+        /// empty ones are written with.
+        nested: Option<Marker>,
+    },
 }
 
 impl<'b> Serializer<'b> {
-    pub fn new_root(buffer: &'b mut dyn io::Write) -> Self {
+    pub fn new_root(buffer: &'b mut dyn io::Write, config: Config) -> Self {
         Self {
             buffer,
             state: State::Empty,
             is_root: true,
+            streamed_blob: None,
+            array_element: None,
+            counter: None,
+            depth: 0,
+            config,
+        }
+    }
+
+    /// Creates a root serializer that writes to `buffer`, which counts into `counter`.
+    pub fn new_counting(
+        buffer: &'b mut dyn io::Write,
+        counter: &'b Counter,
+        config: Config,
+    ) -> Self {
+        Self {
+            counter: Some(counter),
+            ..Self::new_root(buffer, config)
+        }
+    }
+
+    /// Creates a serializer for a value that is buffered before it is written by this one.
+    fn new_nested<'c>(&self, buffer: &'c mut dyn io::Write) -> Serializer<'c>
+    where
+        'b: 'c,
+    {
+        Serializer {
+            buffer,
+            state: State::Empty,
+            is_root: false,
+            streamed_blob: None,
+            array_element: None,
+            counter: self.counter,
+            depth: self.depth,
+            config: self.config,
         }
     }
 
     fn write_marker(&mut self, marker: Marker) -> Result<()> {
+        self.write_element_marker(marker, None)
+    }
+
+    /// Writes the marker of a value, which is an array with the marker `nested` if it is an
+    /// element of an array of arrays.
+    fn write_element_marker(&mut self, marker: Marker, nested: Option<Marker>) -> Result<()> {
         if self.is_root && marker == MARKER_SINGLE_STRUCT {
             self.is_root = false;
 
@@ -43,36 +112,149 @@ impl<'b> Serializer<'b> {
         match self.state {
             State::Empty => self.buffer.write_all(&[marker.to_byte()])?,
             // special case sequences of bytes as strings
-            State::First { length } if marker == MARKER_SINGLE_U8 => {
+            State::First {
+                length,
+                deferred: 0,
+            } if marker == MARKER_SINGLE_U8 => {
                 self.buffer.write_all(&[MARKER_SINGLE_STRING.to_byte()])?;
                 self.buffer.write_all(&crate::varint::encode(length))?;
 
-                self.state = State::Rest;
+                self.state = State::Rest {
+                    marker,
+                    nested: None,
+                };
             }
-            State::First { length } => {
+            State::First { length, deferred } => {
+                self.check_depth()?;
                 self.buffer.write_all(&[marker.to_sequence().to_byte()])?;
                 self.buffer.write_all(&crate::varint::encode(length))?;
 
-                self.state = State::Rest;
+                self.state = State::Rest { marker, nested };
+
+                for _ in 0..deferred {
+                    self.write_empty_element()?;
+                }
+            }
+            State::Rest {
+                marker: expected, ..
+            } if expected != marker => {
+                return Err(Error::array_element_mismatch(expected, marker));
             }
-            State::Rest => {}
+            State::Rest {
+                nested: ref mut first,
+                ..
+            } => {
+                if first.is_none() {
+                    *first = nested;
+                }
+            }
+        };
+
+        Ok(())
+    }
+
+    /// Starts a sequence of the given length, whose marker is written with its first element
+    /// unless the marker of its elements is known ahead of time.
+    fn start_sequence(&mut self, length: usize, element: Option<Marker>) -> Result<()> {
+        if let Some(marker) = element {
+            self.check_depth()?;
+            self.write_marker(marker.to_sequence())?;
+            self.buffer.write_all(&crate::varint::encode(length))?;
+            self.state = State::Rest {
+                marker,
+                nested: None,
+            };
+
+            return Ok(());
+        }
+
+        self.state = State::First {
+            length,
+            deferred: 0,
+        };
+
+        if length == 0 {
+            self.write_marker(MARKER_EMPTY_SEQUENCE)?;
+        }
+
+        Ok(())
+    }
+
+    /// Ends a sequence, whose deferred empty elements are written as blobs if it only consists of
+    /// them.
+    fn end_sequence(&mut self) -> Result<()> {
+        if let State::First { deferred, .. } = self.state {
+            if deferred > 0 {
+                self.write_marker(MARKER_SINGLE_STRING)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Writes an empty sequence as an element of the current array.
+    fn write_empty_element(&mut self) -> Result<()> {
+        let (marker, nested) = match self.state {
+            State::Rest { marker, nested } => (marker, nested),
+            _ => unreachable!("empty elements are written once the type of the array is known"),
         };
 
+        match (marker, nested) {
+            (MARKER_SINGLE_STRING, _) => self.buffer.write_all(&crate::varint::encode(0))?,
+            // epee requires every array to have a type, which can't be told from an empty
+            // sequence, so it is taken from its siblings
+            (MARKER_SINGLE_ARRAY, Some(nested)) => {
+                self.buffer.write_all(&[nested.to_byte()])?;
+                self.buffer.write_all(&crate::varint::encode(0))?;
+            }
+            (MARKER_SINGLE_ARRAY, None) => {
+                return Err(Error::array_element_mismatch(marker, MARKER_EMPTY_SEQUENCE))
+            }
+            _ => return Err(Error::array_element_mismatch(marker, MARKER_SINGLE_ARRAY)),
+        }
+
         Ok(())
     }
+
+    /// Fails if a section or array is written that is nested too deeply, like epee does when
+    /// reading it.
+    ///
+    /// The depth already includes the section or array that is about to be written.
+    fn check_depth(&self) -> Result<()> {
+        if self.depth > RECURSION_LIMIT {
+            return Err(Error::recursion_limit_exceeded());
+        }
+
+        Ok(())
+    }
+
+    /// Writes the start of a section with a single field named after the variant, which holds
+    /// the variant's payload, and returns the state to restore once the payload is written.
+    fn write_variant_header(&mut self, variant: &'static str) -> Result<State> {
+        self.depth += 1;
+        self.check_depth()?;
+        self.write_marker(MARKER_SINGLE_STRUCT)?;
+        let outer_state = self.state.clone();
+
+        self.buffer.write_all(&crate::varint::encode(1))?;
+        write_field_name(self.buffer, variant.as_bytes())?;
+        self.state = State::Empty;
+
+        Ok(outer_state)
+    }
 }
 
 impl<'a, 'b> serde::Serializer for &'a mut Serializer<'b> {
     type Ok = ();
     type Error = Error;
 
-    type SerializeSeq = Self;
-    type SerializeTuple = Self;
-    type SerializeTupleStruct = Self;
-    type SerializeTupleVariant = Self;
-    type SerializeMap = Self;
+    type SerializeSeq = SeqSerializer<'a, 'b>;
+    type SerializeTuple = TupleSerializer<'a, 'b>;
+    type SerializeTupleStruct = TupleStructSerializer<'a, 'b>;
+    type SerializeTupleVariant = TupleVariantSerializer<'a, 'b>;
+    type SerializeMap = MapSerializer<'a, 'b>;
     type SerializeStruct = StructSerializer<'a, 'b>;
-    type SerializeStructVariant = Self;
+    type SerializeStructVariant = StructSerializer<'a, 'b>;
 
     fn serialize_bool(self, v: bool) -> Result<Self::Ok> {
         self.write_marker(MARKER_SINGLE_BOOL)?;
@@ -137,8 +319,20 @@ impl<'a, 'b> serde::Serializer for &'a mut Serializer<'b> {
         Ok(())
     }
 
-    fn serialize_f32(self, _: f32) -> Result<Self::Ok> {
-        Err(Error::f32_is_not_supported())
+    fn serialize_i128(self, _: i128) -> Result<Self::Ok> {
+        Err(Error::i128_is_not_supported())
+    }
+
+    fn serialize_u128(self, _: u128) -> Result<Self::Ok> {
+        Err(Error::u128_is_not_supported())
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<Self::Ok> {
+        if !self.config.widen_f32 {
+            return Err(Error::f32_is_not_supported());
+        }
+
+        self.serialize_f64(v.into())
     }
 
     fn serialize_f64(self, v: f64) -> Result<Self::Ok> {
@@ -149,10 +343,14 @@ impl<'a, 'b> serde::Serializer for &'a mut Serializer<'b> {
     }
 
     fn serialize_char(self, v: char) -> Result<Self::Ok> {
-        self.write_marker(MARKER_SINGLE_U8)?;
-        self.buffer.write_all(&[v as u8])?;
+        match self.config.char_encoding {
+            CharEncoding::Utf8 => self.serialize_str(v.encode_utf8(&mut [0u8; 4])),
+            CharEncoding::Byte => {
+                let byte = u8::try_from(v).map_err(|_| Error::char_out_of_range(v))?;
 
-        Ok(())
+                self.serialize_u8(byte)
+            }
+        }
     }
 
     fn serialize_str(self, v: &str) -> Result<Self::Ok> {
@@ -165,6 +363,17 @@ impl<'a, 'b> serde::Serializer for &'a mut Serializer<'b> {
 
     // epee expects "bytes" to be marked as a string ...
     fn serialize_bytes(self, v: &[u8]) -> Result<Self::Ok> {
+        if let Some(remaining) = self.streamed_blob {
+            if v.len() > remaining {
+                return Err(Error::length_mismatch(remaining, v.len()));
+            }
+
+            self.buffer.write_all(v)?;
+            self.streamed_blob = Some(remaining - v.len());
+
+            return Ok(());
+        }
+
         self.write_marker(MARKER_SINGLE_STRING)?;
         self.buffer.write_all(&crate::varint::encode(v.len()))?;
         self.buffer.write_all(v)?;
@@ -176,80 +385,112 @@ impl<'a, 'b> serde::Serializer for &'a mut Serializer<'b> {
         Err(Error::none_can_not_be_serialized())
     }
 
-    fn serialize_some<T: ?Sized>(self, v: &T) -> Result<Self::Ok>
+    fn serialize_some<T>(self, v: &T) -> Result<Self::Ok>
     where
-        T: Serialize,
+        T: ?Sized + Serialize,
     {
         v.serialize(self)
     }
 
+    // unit and unit structs are encoded as empty sections
     fn serialize_unit(self) -> Result<Self::Ok> {
-        Err(Error::unit_is_not_supported())
+        serde::ser::SerializeStruct::end(self.serialize_struct("", 0)?)
     }
 
     fn serialize_unit_struct(self, _: &'static str) -> Result<Self::Ok> {
         self.serialize_unit()
     }
 
-    fn serialize_unit_variant(self, _: &'static str, _: u32, _: &'static str) -> Result<Self::Ok> {
-        Err(Error::enums_are_not_supported())
+    // unit variants are encoded by their name ...
+    fn serialize_unit_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        variant: &'static str,
+    ) -> Result<Self::Ok> {
+        self.serialize_str(variant)
     }
 
-    fn serialize_newtype_struct<T: ?Sized>(self, _: &'static str, value: &T) -> Result<Self::Ok>
+    fn serialize_newtype_struct<T>(self, name: &'static str, value: &T) -> Result<Self::Ok>
     where
-        T: Serialize,
+        T: ?Sized + Serialize,
     {
+        if let Some(element) = ElementType::from_token(name) {
+            // epee rejects arrays whose elements don't have a type
+            if element == ElementType::Untyped {
+                return Err(Error::untyped_array());
+            }
+
+            self.array_element = Some(element.marker());
+        }
+
         value.serialize(self)
     }
 
-    fn serialize_newtype_variant<T: ?Sized>(
+    fn serialize_newtype_variant<T>(
         self,
         _name: &'static str,
         _variant_index: u32,
-        _variant: &'static str,
-        _value: &T,
+        variant: &'static str,
+        value: &T,
     ) -> Result<Self::Ok>
     where
-        T: Serialize,
+        T: ?Sized + Serialize,
     {
-        Err(Error::enums_are_not_supported())
+        let outer_state = self.write_variant_header(variant)?;
+        value.serialize(&mut *self)?;
+        self.state = outer_state;
+        self.depth -= 1;
+
+        Ok(())
     }
 
     fn serialize_seq(self, len: Option<usize>) -> Result<Self::SerializeSeq> {
         let len = len.ok_or_else(Error::no_length)?;
-        self.state = State::First { length: len };
-
-        if len == 0 {
-            self.write_marker(Marker::Sequence { element: 255 })?;
-        }
 
-        Ok(self)
+        SeqSerializer::new(self, len)
     }
 
     fn serialize_tuple(self, len: usize) -> Result<Self::SerializeTuple> {
-        self.serialize_seq(Some(len))
+        let tuple = match self.config.tuple_encoding {
+            TupleEncoding::Array => TupleSerializer::Array(self.serialize_seq(Some(len))?),
+            TupleEncoding::Section => {
+                TupleSerializer::Section(PositionalSerializer::new(self, len))
+            }
+        };
+
+        Ok(tuple)
     }
 
     fn serialize_tuple_struct(
         self,
-        _: &'static str,
-        _: usize,
+        name: &'static str,
+        len: usize,
     ) -> Result<Self::SerializeTupleStruct> {
-        Err(Error::tuple_structs_are_not_supported())
+        if name == crate::blob::TOKEN {
+            return Ok(TupleStructSerializer::Blob(BlobSerializer::new(self, len)?));
+        }
+
+        Ok(TupleStructSerializer::Positional(
+            PositionalSerializer::new(self, len),
+        ))
     }
 
     fn serialize_tuple_variant(
         self,
         _: &'static str,
         _: u32,
-        _: &'static str,
-        _: usize,
+        variant: &'static str,
+        len: usize,
     ) -> Result<Self::SerializeTupleVariant> {
-        Err(Error::enums_are_not_supported())
+        let outer_state = self.write_variant_header(variant)?;
+        let tuple = self.serialize_tuple(len)?;
+
+        Ok(TupleVariantSerializer { tuple, outer_state })
     }
 
-    fn serialize_map(self, _: Option<usize>) -> Result<Self::SerializeMap> {
-        Err(Error::enums_are_not_supported())
+    fn serialize_map(self, len: Option<usize>) -> Result<Self::SerializeMap> {
+        MapSerializer::new(self, len)
     }
 
     fn serialize_struct(self, _: &'static str, len: usize) -> Result<Self::SerializeStruct> {
@@ -260,162 +501,578 @@ impl<'a, 'b> serde::Serializer for &'a mut Serializer<'b> {
         self,
         _name: &'static str,
         _variant_index: u32,
-        _variant: &'static str,
-        _len: usize,
+        variant: &'static str,
+        len: usize,
     ) -> Result<Self::SerializeStructVariant> {
-        Err(Error::enums_are_not_supported())
+        let outer_state = self.write_variant_header(variant)?;
+        let mut fields = StructSerializer::new(self, len)?;
+        fields.outer_state = outer_state;
+
+        Ok(fields)
+    }
+
+    fn is_human_readable(&self) -> bool {
+        false
+    }
+}
+
+/// Serializes sequences, which are arrays unless they consist of bytes.
+///
+/// Sequences that are elements of an array are buffered, as whether the outer array holds blobs
+/// or arrays depends on their elements.
+pub struct SeqSerializer<'a, 'b> {
+    inner: &'a mut Serializer<'b>,
+    outer_state: State,
+    nested: Option<NestedSeq>,
+}
+
+struct NestedSeq {
+    buffer: Vec<u8>,
+    state: State,
+}
+
+impl NestedSeq {
+    /// Runs `f` with a serializer that writes to the buffer of the sequence.
+    fn serialize_with<F>(&mut self, outer: &Serializer<'_>, f: F) -> Result<()>
+    where
+        F: FnOnce(&mut Serializer<'_>) -> Result<()>,
+    {
+        let mut serializer = outer.new_nested(&mut self.buffer);
+        serializer.state = std::mem::replace(&mut self.state, State::Empty);
+        let result = f(&mut serializer);
+        self.state = serializer.state;
+
+        result
+    }
+}
+
+impl<'a, 'b> SeqSerializer<'a, 'b> {
+    fn new(inner: &'a mut Serializer<'b>, length: usize) -> Result<Self> {
+        let outer_state = inner.state.clone();
+        let element = inner.array_element.take();
+        inner.depth += 1;
+
+        if outer_state != State::Empty {
+            let mut seq = NestedSeq {
+                buffer: Vec::new(),
+                state: State::Empty,
+            };
+            seq.serialize_with(inner, |nested| nested.start_sequence(length, element))?;
+
+            return Ok(Self {
+                inner,
+                outer_state,
+                nested: Some(seq),
+            });
+        }
+
+        inner.start_sequence(length, element)?;
+
+        Ok(Self {
+            inner,
+            outer_state,
+            nested: None,
+        })
+    }
+
+    fn push<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        match &mut self.nested {
+            Some(seq) => seq.serialize_with(self.inner, |nested| value.serialize(nested)),
+            None => value.serialize(&mut *self.inner),
+        }
+    }
+
+    fn finish(self) -> Result<&'a mut Serializer<'b>> {
+        let mut seq = match self.nested {
+            Some(seq) => seq,
+            None => {
+                self.inner.end_sequence()?;
+                self.inner.state = self.outer_state;
+                self.inner.depth -= 1;
+
+                return Ok(self.inner);
+            }
+        };
+
+        seq.serialize_with(self.inner, |nested| nested.end_sequence())?;
+        self.inner.depth -= 1;
+
+        // empty sequences can be blobs or arrays, which is up to the other elements
+        if seq.buffer == [MARKER_EMPTY_SEQUENCE.to_byte(), 0] {
+            match &mut self.inner.state {
+                State::First { deferred, .. } => *deferred += 1,
+                State::Rest { .. } => self.inner.write_empty_element()?,
+                State::Empty => unreachable!("nested sequences are elements of an array"),
+            }
+
+            return Ok(self.inner);
+        }
+
+        // blobs are elements of an array of strings without their own marker, all other
+        // sequences are elements of an array of arrays and keep theirs
+        if seq.buffer.first() == Some(&MARKER_SINGLE_STRING.to_byte()) {
+            self.inner.write_marker(MARKER_SINGLE_STRING)?;
+            self.inner.buffer.write_all(&seq.buffer[1..])?;
+        } else {
+            let nested = Marker::from_byte(seq.buffer[0]);
+            self.inner
+                .write_element_marker(MARKER_SINGLE_ARRAY, Some(nested))?;
+            self.inner.buffer.write_all(&seq.buffer)?;
+        }
+
+        Ok(self.inner)
     }
 }
 
-impl<'a, 'b> serde::ser::SerializeSeq for &'a mut Serializer<'b> {
+impl<'a, 'b> serde::ser::SerializeSeq for SeqSerializer<'a, 'b> {
     type Ok = ();
     type Error = Error;
 
-    fn serialize_element<T: ?Sized>(&mut self, value: &T) -> Result<()>
+    fn serialize_element<T>(&mut self, value: &T) -> Result<()>
     where
-        T: Serialize,
+        T: ?Sized + Serialize,
     {
-        value.serialize(&mut **self)?;
-
-        Ok(())
+        self.push(value)
     }
 
     fn end(self) -> Result<Self::Ok> {
-        self.state = State::Empty;
+        self.finish()?;
 
         Ok(())
     }
 }
 
-impl<'a, 'b> serde::ser::SerializeTuple for &'a mut Serializer<'b> {
+pub enum TupleSerializer<'a, 'b> {
+    Array(SeqSerializer<'a, 'b>),
+    Section(PositionalSerializer<'a, 'b>),
+}
+
+impl<'a, 'b> TupleSerializer<'a, 'b> {
+    fn push<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        match self {
+            TupleSerializer::Array(elements) => elements.push(value),
+            TupleSerializer::Section(fields) => fields.push(value),
+        }
+    }
+
+    fn finish(self) -> Result<&'a mut Serializer<'b>> {
+        match self {
+            TupleSerializer::Array(elements) => elements.finish(),
+            TupleSerializer::Section(fields) => fields.finish(),
+        }
+    }
+}
+
+impl<'a, 'b> serde::ser::SerializeTuple for TupleSerializer<'a, 'b> {
     type Ok = ();
     type Error = Error;
 
-    fn serialize_element<T: ?Sized>(&mut self, value: &T) -> Result<()>
+    fn serialize_element<T>(&mut self, value: &T) -> Result<()>
     where
-        T: Serialize,
+        T: ?Sized + Serialize,
     {
-        serde::ser::SerializeSeq::serialize_element(self, value)
+        self.push(value)
     }
 
     fn end(self) -> Result<Self::Ok> {
-        serde::ser::SerializeSeq::end(self)
+        self.finish()?;
+
+        Ok(())
     }
 }
 
-impl<'a, 'b> serde::ser::SerializeTupleStruct for &'a mut Serializer<'b> {
+pub struct StructSerializer<'a, 'b> {
+    inner: &'a mut Serializer<'b>,
+    outer_state: State,
+}
+
+impl<'a, 'b> StructSerializer<'a, 'b> {
+    fn new(inner: &'a mut Serializer<'b>, number_of_fields: usize) -> Result<Self> {
+        inner.depth += 1;
+        inner.check_depth()?;
+        inner.write_marker(MARKER_SINGLE_STRUCT)?;
+        inner
+            .buffer
+            .write_all(&crate::varint::encode(number_of_fields))?;
+
+        let current_state = inner.state.clone();
+        inner.state = State::Empty;
+
+        Ok(Self {
+            inner,
+            outer_state: current_state,
+        })
+    }
+}
+
+impl<'a, 'b> serde::ser::SerializeStruct for StructSerializer<'a, 'b> {
     type Ok = ();
     type Error = Error;
 
-    fn serialize_field<T: ?Sized>(&mut self, _value: &T) -> Result<()>
+    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<()>
     where
-        T: Serialize,
+        T: ?Sized + Serialize,
     {
-        unreachable!()
+        write_field_name(self.inner.buffer, key.as_bytes())?;
+
+        value.serialize(&mut *self.inner)?;
+
+        Ok(())
     }
 
     fn end(self) -> Result<Self::Ok> {
-        unreachable!()
+        self.inner.state = self.outer_state;
+        self.inner.depth -= 1;
+
+        Ok(())
     }
 }
 
-impl<'a, 'b> serde::ser::SerializeTupleVariant for &'a mut Serializer<'b> {
+impl<'a, 'b> serde::ser::SerializeStructVariant for StructSerializer<'a, 'b> {
     type Ok = ();
     type Error = Error;
 
-    fn serialize_field<T: ?Sized>(&mut self, _value: &T) -> Result<()>
+    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<()>
     where
-        T: Serialize,
+        T: ?Sized + Serialize,
     {
-        unreachable!()
+        serde::ser::SerializeStruct::serialize_field(self, key, value)
     }
 
     fn end(self) -> Result<Self::Ok> {
-        unreachable!()
+        // the section that holds the variant
+        self.inner.depth -= 1;
+
+        serde::ser::SerializeStruct::end(self)
     }
 }
 
-impl<'a, 'b> serde::ser::SerializeMap for &'a mut Serializer<'b> {
+pub struct TupleVariantSerializer<'a, 'b> {
+    tuple: TupleSerializer<'a, 'b>,
+    outer_state: State,
+}
+
+impl<'a, 'b> serde::ser::SerializeTupleVariant for TupleVariantSerializer<'a, 'b> {
     type Ok = ();
     type Error = Error;
 
-    fn serialize_key<T: ?Sized>(&mut self, _key: &T) -> Result<()>
+    fn serialize_field<T>(&mut self, value: &T) -> Result<()>
     where
-        T: Serialize,
+        T: ?Sized + Serialize,
     {
-        unreachable!()
+        self.tuple.push(value)
+    }
+
+    fn end(self) -> Result<Self::Ok> {
+        let inner = self.tuple.finish()?;
+        inner.state = self.outer_state;
+        inner.depth -= 1;
+
+        Ok(())
     }
+}
+
+/// Serializes tuple structs and, with [`TupleEncoding::Section`], tuples.
+///
+/// Tuples of bytes are encoded as a blob. All others are encoded as a section whose fields are
+/// named after their position, i.e. `"0"`, `"1"` and so on. As the encoding depends on the types
+/// of all fields, they are buffered until the end.
+pub struct PositionalSerializer<'a, 'b> {
+    inner: &'a mut Serializer<'b>,
+    fields: Vec<Vec<u8>>,
+}
+
+impl<'a, 'b> PositionalSerializer<'a, 'b> {
+    fn new(inner: &'a mut Serializer<'b>, number_of_fields: usize) -> Self {
+        inner.depth += 1;
 
-    fn serialize_value<T: ?Sized>(&mut self, _value: &T) -> Result<()>
+        Self {
+            inner,
+            fields: Vec::with_capacity(number_of_fields),
+        }
+    }
+
+    fn push<T>(&mut self, value: &T) -> Result<()>
     where
-        T: Serialize,
+        T: ?Sized + Serialize,
     {
-        unreachable!()
+        let mut field = Vec::new();
+        value.serialize(&mut self.inner.new_nested(&mut field))?;
+        self.fields.push(field);
+
+        Ok(())
     }
 
-    fn end(self) -> Result<Self::Ok> {
-        unreachable!()
+    fn finish(self) -> Result<&'a mut Serializer<'b>> {
+        let is_byte = |field: &Vec<u8>| field.len() == 2 && field[0] == MARKER_SINGLE_U8.to_byte();
+
+        if !self.fields.is_empty() && self.fields.iter().all(is_byte) {
+            let bytes = self.fields.iter().map(|field| field[1]).collect::<Vec<_>>();
+            self.inner.depth -= 1;
+            serde::Serializer::serialize_bytes(&mut *self.inner, &bytes)?;
+
+            return Ok(self.inner);
+        }
+
+        self.inner.check_depth()?;
+        self.inner.write_marker(MARKER_SINGLE_STRUCT)?;
+        self.inner
+            .buffer
+            .write_all(&crate::varint::encode(self.fields.len()))?;
+
+        for (position, field) in self.fields.iter().enumerate() {
+            write_field_name(self.inner.buffer, position.to_string().as_bytes())?;
+            self.inner.buffer.write_all(field)?;
+        }
+        self.inner.depth -= 1;
+
+        Ok(self.inner)
     }
 }
 
-impl<'a, 'b> serde::ser::SerializeStructVariant for &'a mut Serializer<'b> {
+pub enum TupleStructSerializer<'a, 'b> {
+    Positional(PositionalSerializer<'a, 'b>),
+    Blob(BlobSerializer<'a, 'b>),
+}
+
+impl<'a, 'b> serde::ser::SerializeTupleStruct for TupleStructSerializer<'a, 'b> {
     type Ok = ();
     type Error = Error;
 
-    fn serialize_field<T: ?Sized>(&mut self, _key: &'static str, _value: &T) -> Result<()>
+    fn serialize_field<T>(&mut self, value: &T) -> Result<()>
     where
-        T: Serialize,
+        T: ?Sized + Serialize,
     {
-        unreachable!()
+        match self {
+            TupleStructSerializer::Positional(fields) => fields.push(value),
+            TupleStructSerializer::Blob(blob) => blob.push(value),
+        }
     }
 
     fn end(self) -> Result<Self::Ok> {
-        unreachable!()
+        match self {
+            TupleStructSerializer::Positional(fields) => {
+                fields.finish()?;
+            }
+            TupleStructSerializer::Blob(blob) => blob.finish()?,
+        }
+
+        Ok(())
     }
 }
 
-pub struct StructSerializer<'a, 'b> {
+/// Streams a [`ReaderBlob`](crate::blob::ReaderBlob), whose chunks are serialized as fields of a
+/// tuple struct named [`TOKEN`](crate::blob::TOKEN) whose length is the length of the blob.
+pub struct BlobSerializer<'a, 'b> {
     inner: &'a mut Serializer<'b>,
+    length: usize,
+    remaining: usize,
+}
+
+impl<'a, 'b> BlobSerializer<'a, 'b> {
+    fn new(inner: &'a mut Serializer<'b>, length: usize) -> Result<Self> {
+        inner.write_marker(MARKER_SINGLE_STRING)?;
+        inner.buffer.write_all(&crate::varint::encode(length))?;
+
+        Ok(Self {
+            inner,
+            length,
+            remaining: length,
+        })
+    }
+
+    fn push<T>(&mut self, chunk: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        // the size is known upfront, don't consume the source
+        if self.inner.counter.is_some() {
+            return Ok(());
+        }
+
+        self.inner.streamed_blob = Some(self.remaining);
+        let result = chunk.serialize(&mut *self.inner);
+        self.remaining = self.inner.streamed_blob.take().unwrap_or(self.remaining);
+
+        result
+    }
+
+    fn finish(self) -> Result<()> {
+        if let Some(counter) = self.inner.counter {
+            counter.skip(self.remaining);
+
+            return Ok(());
+        }
+
+        if self.remaining != 0 {
+            return Err(Error::length_mismatch(
+                self.length,
+                self.length - self.remaining,
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+pub struct MapSerializer<'a, 'b> {
+    inner: &'a mut Serializer<'b>,
+    /// Maps of unknown length (like structs containing `#[serde(flatten)]` fields) are buffered
+    /// because epee writes the number of fields before the fields themselves.
+    buffered: Option<BufferedEntries>,
     outer_state: State,
 }
 
-impl<'a, 'b> StructSerializer<'a, 'b> {
-    fn new(inner: &'a mut Serializer<'b>, number_of_fields: usize) -> Result<Self> {
-        inner.write_marker(MARKER_SINGLE_STRUCT)?;
-        inner
-            .buffer
-            .write_all(&crate::varint::encode(number_of_fields))?;
+struct BufferedEntries {
+    buffer: Vec<u8>,
+    number_of_fields: usize,
+}
 
-        let current_state = inner.state.clone();
-        inner.state = State::Empty;
+impl<'a, 'b> MapSerializer<'a, 'b> {
+    fn new(inner: &'a mut Serializer<'b>, number_of_fields: Option<usize>) -> Result<Self> {
+        inner.depth += 1;
+        inner.check_depth()?;
+
+        let buffered = match number_of_fields {
+            Some(number_of_fields) => {
+                inner.write_marker(MARKER_SINGLE_STRUCT)?;
+                inner
+                    .buffer
+                    .write_all(&crate::varint::encode(number_of_fields))?;
+
+                None
+            }
+            None => Some(BufferedEntries {
+                buffer: Vec::new(),
+                number_of_fields: 0,
+            }),
+        };
+
+        let outer_state = inner.state.clone();
+        if buffered.is_none() {
+            inner.state = State::Empty;
+        }
 
         Ok(Self {
             inner,
-            outer_state: current_state,
+            buffered,
+            outer_state,
         })
     }
 }
 
-impl<'a, 'b> serde::ser::SerializeStruct for StructSerializer<'a, 'b> {
+impl<'a, 'b> serde::ser::SerializeMap for MapSerializer<'a, 'b> {
     type Ok = ();
     type Error = Error;
 
-    fn serialize_field<T: ?Sized>(&mut self, key: &'static str, value: &T) -> Result<()>
+    fn serialize_key<T>(&mut self, key: &T) -> Result<()>
     where
-        T: Serialize,
+        T: ?Sized + Serialize,
     {
-        let name_length = key.len() as u8;
-        self.inner.buffer.write_all(&[name_length])?;
-        self.inner.buffer.write_all(key.as_bytes())?;
+        let key = serialize_map_key(key, self.inner)?;
 
-        value.serialize(&mut *self.inner)?;
+        match &mut self.buffered {
+            Some(entries) => {
+                write_field_name(&mut entries.buffer, &key)?;
+                entries.number_of_fields += 1;
+            }
+            None => write_field_name(self.inner.buffer, &key)?,
+        }
 
         Ok(())
     }
 
+    fn serialize_value<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        match &mut self.buffered {
+            Some(entries) => value.serialize(&mut self.inner.new_nested(&mut entries.buffer)),
+            None => value.serialize(&mut *self.inner),
+        }
+    }
+
     fn end(self) -> Result<Self::Ok> {
-        self.inner.state = self.outer_state;
+        self.inner.depth -= 1;
+
+        match self.buffered {
+            Some(entries) => {
+                self.inner.write_marker(MARKER_SINGLE_STRUCT)?;
+                self.inner
+                    .buffer
+                    .write_all(&crate::varint::encode(entries.number_of_fields))?;
+                self.inner.buffer.write_all(&entries.buffer)?;
+            }
+            None => self.inner.state = self.outer_state,
+        }
+
+        Ok(())
+    }
+}
+
+/// Serializes a map key, which has to be a string to be usable as the name of a section field.
+fn serialize_map_key<T>(key: &T, outer: &Serializer<'_>) -> Result<Vec<u8>>
+where
+    T: ?Sized + Serialize,
+{
+    let mut buffer = Vec::new();
+    key.serialize(&mut outer.new_nested(&mut buffer))?;
+
+    let (marker, mut bytes) = buffer.split_first().ok_or_else(|| {
+        <Error as serde::ser::Error>::custom("map key did not serialize to a value")
+    })?;
+    let marker = Marker::from_byte(*marker);
+    if marker != MARKER_SINGLE_STRING {
+        return Err(Error::key_must_be_a_string(marker));
+    }
+    crate::varint::decode(&mut bytes)?;
+
+    Ok(bytes.to_vec())
+}
+
+fn write_field_name(buffer: &mut dyn io::Write, name: &[u8]) -> Result<()> {
+    if name.len() > MAX_FIELD_NAME_LEN {
+        return Err(Error::invalid_field_name_length(name.len()));
+    }
+
+    let name_length = name.len() as u8;
+    buffer.write_all(&[name_length])?;
+    buffer.write_all(name)?;
+
+    Ok(())
+}
+
+/// A writer that only counts the bytes written to it.
+#[derive(Default)]
+pub struct Counter {
+    count: Cell<usize>,
+}
+
+impl Counter {
+    pub fn count(&self) -> usize {
+        self.count.get()
+    }
+
+    /// Counts bytes that are not written, like the contents of a streamed blob.
+    fn skip(&self, length: usize) {
+        self.count.set(self.count.get() + length);
+    }
+}
+
+impl io::Write for &Counter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.skip(buf.len());
+
+        Ok(buf.len())
+    }
 
+    fn flush(&mut self) -> io::Result<()> {
         Ok(())
     }
 }
@@ -423,6 +1080,7 @@ impl<'a, 'b> serde::ser::SerializeStruct for StructSerializer<'a, 'b> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use serde::ser::SerializeSeq as _;
     use serde::Serializer as _;
 
     #[test]
@@ -432,6 +1090,11 @@ mod tests {
             buffer: &mut buffer,
             state: State::Empty,
             is_root: false,
+            streamed_blob: None,
+            array_element: None,
+            counter: None,
+            depth: 0,
+            config: Config::default(),
         };
 
         (&mut serializer).serialize_bool(true).unwrap();
@@ -449,14 +1112,19 @@ mod tests {
             buffer: &mut buffer,
             state: State::Empty,
             is_root: false,
+            streamed_blob: None,
+            array_element: None,
+            counter: None,
+            depth: 0,
+            config: Config::default(),
         };
 
         let ser = &mut serializer;
-        let seq = ser.serialize_seq(Some(3)).unwrap();
-        seq.serialize_bool(true).unwrap();
-        seq.serialize_bool(true).unwrap();
-        seq.serialize_bool(true).unwrap();
-        serde::ser::SerializeSeq::end(seq).unwrap();
+        let mut seq = ser.serialize_seq(Some(3)).unwrap();
+        seq.serialize_element(&true).unwrap();
+        seq.serialize_element(&true).unwrap();
+        seq.serialize_element(&true).unwrap();
+        seq.end().unwrap();
 
         let expected_buffer_content = vec![11 | 0x80, 0x0c, 0x01, 0x01, 0x01];
 
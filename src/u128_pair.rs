@@ -0,0 +1,130 @@
+//! Helpers to (de)serialize a `u128` as a pair of `u64` fields.
+//!
+//! epee has no 128-bit integer type, Monero therefore splits values like the cumulative difficulty
+//! into the low 64 bits (e.g. `cumulative_difficulty`) and the high 64 bits (e.g.
+//! `cumulative_difficulty_top64`) stored side by side in the same section. The modules in here
+//! read and write such a pair from and to a single `u128` field. As the pair lives in the
+//! enclosing section, the field needs to be flattened:
+//!
+//! ```
+//! # use serde::{Deserialize, Serialize};
+//! #[derive(Deserialize, Serialize)]
+//! pub struct CoreSyncData {
+//!     #[serde(flatten, with = "monero_epee_bin_serde::u128_pair::cumulative_difficulty")]
+//!     pub cumulative_difficulty: u128,
+//!     pub current_height: u64,
+//! }
+//! ```
+//!
+//! For other field names, wrap [`serialize_fields`] and [`deserialize_fields`] in a module of your
+//! own.
+
+use serde::de::{Error as DeError, IgnoredAny, MapAccess, Visitor};
+use serde::ser::SerializeStruct;
+use serde::{Deserializer, Serializer};
+use std::fmt;
+
+/// `cumulative_difficulty` and `cumulative_difficulty_top64`.
+pub mod cumulative_difficulty {
+    use serde::{Deserializer, Serializer};
+
+    const FIELDS: &[&str; 2] = &["cumulative_difficulty", "cumulative_difficulty_top64"];
+
+    pub fn deserialize<'de, D>(d: D) -> Result<u128, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        super::deserialize_fields(d, FIELDS)
+    }
+
+    pub fn serialize<S>(value: &u128, s: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        super::serialize_fields(value, FIELDS, s)
+    }
+}
+
+/// `difficulty` and `difficulty_top64`.
+pub mod difficulty {
+    use serde::{Deserializer, Serializer};
+
+    const FIELDS: &[&str; 2] = &["difficulty", "difficulty_top64"];
+
+    pub fn deserialize<'de, D>(d: D) -> Result<u128, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        super::deserialize_fields(d, FIELDS)
+    }
+
+    pub fn serialize<S>(value: &u128, s: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        super::serialize_fields(value, FIELDS, s)
+    }
+}
+
+/// Deserializes a `u128` from the fields named `[low, high]`.
+///
+/// Like in Monero, a missing high field is treated as `0`.
+pub fn deserialize_fields<'de, D>(
+    d: D,
+    fields: &'static [&'static str; 2],
+) -> Result<u128, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    d.deserialize_struct("u128", fields, PairVisitor { fields })
+}
+
+/// Serializes a `u128` into the fields named `[low, high]`.
+pub fn serialize_fields<S>(
+    value: &u128,
+    fields: &'static [&'static str; 2],
+    s: S,
+) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    let mut pair = s.serialize_struct("u128", 2)?;
+    pair.serialize_field(fields[0], &(*value as u64))?;
+    pair.serialize_field(fields[1], &((*value >> 64) as u64))?;
+    pair.end()
+}
+
+struct PairVisitor {
+    fields: &'static [&'static str; 2],
+}
+
+impl<'de> Visitor<'de> for PairVisitor {
+    type Value = u128;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "the fields {} and {}", self.fields[0], self.fields[1])
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        let mut low = None;
+        let mut high = None;
+
+        while let Some(key) = map.next_key::<String>()? {
+            if key == self.fields[0] {
+                low = Some(map.next_value::<u64>()?);
+            } else if key == self.fields[1] {
+                high = Some(map.next_value::<u64>()?);
+            } else {
+                map.next_value::<IgnoredAny>()?;
+            }
+        }
+
+        let low = low.ok_or_else(|| DeError::missing_field(self.fields[0]))?;
+        let high = high.unwrap_or_default();
+
+        Ok((high as u128) << 64 | low as u128)
+    }
+}
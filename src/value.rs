@@ -0,0 +1,919 @@
+//! A dynamically typed representation of epee data.
+//!
+//! [`Value`] keeps the exact epee type of every value, so deserializing into a [`Value`] and
+//! serializing it again reproduces the input. [`WithUnknownFields`] captures the fields of a
+//! section that a struct doesn't know about, e.g. fields added by a newer version of monerod, and
+//! writes them back when the struct is serialized:
+//!
+//! ```
+//! use monero_epee_bin_serde::value::WithUnknownFields;
+//! use serde::{Deserialize, Serialize};
+//!
+//! #[derive(Deserialize, Serialize)]
+//! struct TimedSyncRequest {
+//!     payload_data: u64,
+//! }
+//!
+//! type Request = WithUnknownFields<TimedSyncRequest>;
+//! ```
+//!
+//! [`UnknownFields`] can also be captured with `#[serde(flatten)]`, which buffers the values in a
+//! format-independent way that loses the element type of empty arrays. Serializing such an array
+//! fails, because epee rejects arrays without an element type.
+
+use crate::{Error, Marker};
+use serde::de::value::{MapDeserializer, SeqDeserializer};
+use serde::de::{
+    DeserializeSeed, EnumAccess, IntoDeserializer, MapAccess, SeqAccess, VariantAccess, Visitor,
+};
+use serde::ser::{Impossible, SerializeMap, SerializeSeq, SerializeStruct};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use std::marker::PhantomData;
+
+/// Name of the type that the deserializer passes the element type of arrays to.
+pub(crate) const TOKEN: &str = "$monero_epee_bin_serde::private::Value";
+
+/// Name of the type that the deserializer passes the fields of a section to that a type ignored.
+pub(crate) const FIELDS_TOKEN: &str = "$monero_epee_bin_serde::private::WithUnknownFields";
+
+/// The result of [`Config::from_slice_lenient`](crate::Config::from_slice_lenient).
+#[derive(Debug)]
+pub struct Partial {
+    /// The root section with everything that could be parsed before the first error that parsing
+    /// can't recover from.
+    ///
+    /// Sections and arrays that were cut short by an error hold the values parsed until then,
+    /// values that were cut short are missing.
+    pub section: Section,
+    /// The errors that were encountered together with the offset into the input they relate to.
+    ///
+    /// Parsing continues after errors that don't affect the structure of the input, like an
+    /// invalid header or field name. It stops at the first other error, which is the last one.
+    pub errors: Vec<(usize, Error)>,
+}
+
+impl Partial {
+    /// Whether the input was parsed without errors.
+    pub fn is_complete(&self) -> bool {
+        self.errors.is_empty()
+    }
+}
+
+/// Any value that can be encoded in epee.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    I64(i64),
+    I32(i32),
+    I16(i16),
+    I8(i8),
+    U64(u64),
+    U32(u32),
+    U16(u16),
+    U8(u8),
+    F64(f64),
+    /// Epee strings hold arbitrary bytes, which are not necessarily UTF-8.
+    String(Vec<u8>),
+    Bool(bool),
+    Section(Section),
+    /// An array, whose elements all have the type `element`.
+    ///
+    /// Other formats and `#[serde(flatten)]`, which buffers values in a format-independent way,
+    /// don't retain the type of the elements. It is then taken from the first element, and empty
+    /// arrays are [`ElementType::Untyped`], which can't be serialized as epee.
+    Array {
+        element: ElementType,
+        values: Vec<Value>,
+    },
+}
+
+/// The type of the elements of a [`Value::Array`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ElementType {
+    I64,
+    I32,
+    I16,
+    I8,
+    U64,
+    U32,
+    U16,
+    U8,
+    F64,
+    String,
+    Bool,
+    Section,
+    Array,
+    /// The elements of empty arrays whose type is unknown, like empty sequences that this crate
+    /// serialized without knowing their type.
+    ///
+    /// Epee rejects arrays of this type, so serializing them fails.
+    Untyped,
+}
+
+impl ElementType {
+    const ALL: [ElementType; 14] = [
+        ElementType::I64,
+        ElementType::I32,
+        ElementType::I16,
+        ElementType::I8,
+        ElementType::U64,
+        ElementType::U32,
+        ElementType::U16,
+        ElementType::U8,
+        ElementType::F64,
+        ElementType::String,
+        ElementType::Bool,
+        ElementType::Section,
+        ElementType::Array,
+        ElementType::Untyped,
+    ];
+
+    /// The type of the elements of an array whose first element is `value`.
+    fn of(value: &Value) -> Self {
+        match value {
+            Value::I64(_) => ElementType::I64,
+            Value::I32(_) => ElementType::I32,
+            Value::I16(_) => ElementType::I16,
+            Value::I8(_) => ElementType::I8,
+            Value::U64(_) => ElementType::U64,
+            Value::U32(_) => ElementType::U32,
+            Value::U16(_) => ElementType::U16,
+            Value::U8(_) => ElementType::U8,
+            Value::F64(_) => ElementType::F64,
+            Value::String(_) => ElementType::String,
+            Value::Bool(_) => ElementType::Bool,
+            Value::Section(_) => ElementType::Section,
+            Value::Array { .. } => ElementType::Array,
+        }
+    }
+
+    /// The marker of the elements.
+    pub(crate) fn marker(self) -> Marker {
+        let value = match self {
+            ElementType::I64 => 1,
+            ElementType::I32 => 2,
+            ElementType::I16 => 3,
+            ElementType::I8 => 4,
+            ElementType::U64 => 5,
+            ElementType::U32 => 6,
+            ElementType::U16 => 7,
+            ElementType::U8 => 8,
+            ElementType::F64 => 9,
+            ElementType::String => 10,
+            ElementType::Bool => 11,
+            ElementType::Section => 12,
+            ElementType::Array => 13,
+            ElementType::Untyped => 0x7f,
+        };
+
+        Marker::Single { value }
+    }
+
+    pub(crate) fn from_marker(element: u8) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|ty| ty.marker() == Marker::Single { value: element })
+    }
+
+    /// Name of the type that passes the element type of arrays to the serializer.
+    fn token(self) -> &'static str {
+        match self {
+            ElementType::I64 => "$monero_epee_bin_serde::private::Array<i64>",
+            ElementType::I32 => "$monero_epee_bin_serde::private::Array<i32>",
+            ElementType::I16 => "$monero_epee_bin_serde::private::Array<i16>",
+            ElementType::I8 => "$monero_epee_bin_serde::private::Array<i8>",
+            ElementType::U64 => "$monero_epee_bin_serde::private::Array<u64>",
+            ElementType::U32 => "$monero_epee_bin_serde::private::Array<u32>",
+            ElementType::U16 => "$monero_epee_bin_serde::private::Array<u16>",
+            ElementType::U8 => "$monero_epee_bin_serde::private::Array<u8>",
+            ElementType::F64 => "$monero_epee_bin_serde::private::Array<f64>",
+            ElementType::String => "$monero_epee_bin_serde::private::Array<String>",
+            ElementType::Bool => "$monero_epee_bin_serde::private::Array<bool>",
+            ElementType::Section => "$monero_epee_bin_serde::private::Array<Section>",
+            ElementType::Array => "$monero_epee_bin_serde::private::Array<Array>",
+            ElementType::Untyped => "$monero_epee_bin_serde::private::Array<_>",
+        }
+    }
+
+    pub(crate) fn from_token(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|ty| ty.token() == name)
+    }
+}
+
+/// The fields of a section in the order they appear in the input.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Section {
+    fields: Vec<(String, Value)>,
+}
+
+/// The fields of a section that are not captured by the other fields of a struct.
+///
+/// See the [module documentation](self) for an example.
+pub type UnknownFields = Section;
+
+/// A struct together with the fields of its section that it doesn't know about, which are
+/// written after the fields of the struct when it is serialized.
+///
+/// Unlike flattening [`UnknownFields`] into the struct, this keeps the element type of empty
+/// arrays. Other formats only deserialize `known`, ignoring unknown fields.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct WithUnknownFields<T> {
+    pub known: T,
+    pub unknown: UnknownFields,
+}
+
+impl Section {
+    /// Creates an empty section.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The number of fields in the section.
+    pub fn len(&self) -> usize {
+        self.fields.len()
+    }
+
+    /// Whether the section has no fields.
+    pub fn is_empty(&self) -> bool {
+        self.fields.is_empty()
+    }
+
+    /// Returns the value of the field with the given name.
+    pub fn get(&self, name: &str) -> Option<&Value> {
+        self.fields
+            .iter()
+            .find(|(field, _)| field == name)
+            .map(|(_, value)| value)
+    }
+
+    /// Sets the value of the field with the given name, appending the field if it doesn't exist
+    /// yet, and returns the previous value.
+    pub fn insert(&mut self, name: impl Into<String>, value: Value) -> Option<Value> {
+        let name = name.into();
+
+        match self.fields.iter_mut().find(|(field, _)| *field == name) {
+            Some((_, previous)) => Some(std::mem::replace(previous, value)),
+            None => {
+                self.fields.push((name, value));
+
+                None
+            }
+        }
+    }
+
+    /// Appends a field, even if a field with the same name already exists, like sections in the
+    /// input may do.
+    pub(crate) fn push(&mut self, name: String, value: Value) {
+        self.fields.push((name, value));
+    }
+
+    /// Iterates over the names and values of the fields in order.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &Value)> {
+        self.fields
+            .iter()
+            .map(|(name, value)| (name.as_str(), value))
+    }
+}
+
+impl FromIterator<(String, Value)> for Section {
+    fn from_iter<T: IntoIterator<Item = (String, Value)>>(iter: T) -> Self {
+        let mut section = Section::new();
+        for (name, value) in iter {
+            section.insert(name, value);
+        }
+
+        section
+    }
+}
+
+impl IntoIterator for Section {
+    type Item = (String, Value);
+    type IntoIter = std::vec::IntoIter<(String, Value)>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.fields.into_iter()
+    }
+}
+
+impl Serialize for Value {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match self {
+            Value::I64(v) => serializer.serialize_i64(*v),
+            Value::I32(v) => serializer.serialize_i32(*v),
+            Value::I16(v) => serializer.serialize_i16(*v),
+            Value::I8(v) => serializer.serialize_i8(*v),
+            Value::U64(v) => serializer.serialize_u64(*v),
+            Value::U32(v) => serializer.serialize_u32(*v),
+            Value::U16(v) => serializer.serialize_u16(*v),
+            Value::U8(v) => serializer.serialize_u8(*v),
+            Value::F64(v) => serializer.serialize_f64(*v),
+            Value::String(v) => serializer.serialize_bytes(v),
+            Value::Bool(v) => serializer.serialize_bool(*v),
+            Value::Section(v) => v.serialize(serializer),
+            Value::Array { element, values } => {
+                serializer.serialize_newtype_struct(element.token(), &Elements(values))
+            }
+        }
+    }
+}
+
+/// The elements of a [`Value::Array`], which are a sequence in any format.
+struct Elements<'a>(&'a [Value]);
+
+impl Serialize for Elements<'_> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut seq = serializer.serialize_seq(Some(self.0.len()))?;
+        for value in self.0 {
+            seq.serialize_element(value)?;
+        }
+
+        seq.end()
+    }
+}
+
+impl Serialize for Section {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut map = serializer.serialize_map(Some(self.fields.len()))?;
+        for (name, value) in &self.fields {
+            map.serialize_entry(name, value)?;
+        }
+
+        map.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for Value {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_newtype_struct(TOKEN, ValueVisitor)
+    }
+}
+
+impl<'de> Deserialize<'de> for Section {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_map(SectionVisitor)
+    }
+}
+
+impl<T> Serialize for WithUnknownFields<T>
+where
+    T: Serialize,
+{
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        self.known.serialize(FieldsSerializer {
+            serializer,
+            unknown: &self.unknown,
+        })
+    }
+}
+
+impl<'de, T> Deserialize<'de> for WithUnknownFields<T>
+where
+    T: Deserialize<'de>,
+{
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_newtype_struct(FIELDS_TOKEN, WithUnknownFieldsVisitor(PhantomData))
+    }
+}
+
+struct ValueVisitor;
+
+impl<'de> Visitor<'de> for ValueVisitor {
+    type Value = Value;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "an epee value")
+    }
+
+    fn visit_bool<E>(self, v: bool) -> Result<Self::Value, E> {
+        Ok(Value::Bool(v))
+    }
+
+    fn visit_i8<E>(self, v: i8) -> Result<Self::Value, E> {
+        Ok(Value::I8(v))
+    }
+
+    fn visit_i16<E>(self, v: i16) -> Result<Self::Value, E> {
+        Ok(Value::I16(v))
+    }
+
+    fn visit_i32<E>(self, v: i32) -> Result<Self::Value, E> {
+        Ok(Value::I32(v))
+    }
+
+    fn visit_i64<E>(self, v: i64) -> Result<Self::Value, E> {
+        Ok(Value::I64(v))
+    }
+
+    fn visit_u8<E>(self, v: u8) -> Result<Self::Value, E> {
+        Ok(Value::U8(v))
+    }
+
+    fn visit_u16<E>(self, v: u16) -> Result<Self::Value, E> {
+        Ok(Value::U16(v))
+    }
+
+    fn visit_u32<E>(self, v: u32) -> Result<Self::Value, E> {
+        Ok(Value::U32(v))
+    }
+
+    fn visit_u64<E>(self, v: u64) -> Result<Self::Value, E> {
+        Ok(Value::U64(v))
+    }
+
+    fn visit_f64<E>(self, v: f64) -> Result<Self::Value, E> {
+        Ok(Value::F64(v))
+    }
+
+    fn visit_str<E>(self, v: &str) -> Result<Self::Value, E> {
+        Ok(Value::String(v.as_bytes().to_vec()))
+    }
+
+    fn visit_string<E>(self, v: String) -> Result<Self::Value, E> {
+        Ok(Value::String(v.into_bytes()))
+    }
+
+    fn visit_bytes<E>(self, v: &[u8]) -> Result<Self::Value, E> {
+        Ok(Value::String(v.to_vec()))
+    }
+
+    fn visit_byte_buf<E>(self, v: Vec<u8>) -> Result<Self::Value, E> {
+        Ok(Value::String(v))
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        let mut values = Vec::new();
+        while let Some(value) = seq.next_element()? {
+            values.push(value);
+        }
+
+        let element = values.first().map_or(ElementType::Untyped, ElementType::of);
+
+        Ok(Value::Array { element, values })
+    }
+
+    // arrays whose element type is known, see `deserialize_newtype_struct` of the deserializer
+    fn visit_enum<A>(self, data: A) -> Result<Self::Value, A::Error>
+    where
+        A: EnumAccess<'de>,
+    {
+        let (element, values) = data.variant::<u8>()?;
+        let element = ElementType::from_marker(element).ok_or_else(|| {
+            serde::de::Error::custom(format_args!("unknown element marker {:x}", element))
+        })?;
+
+        Ok(Value::Array {
+            element,
+            values: values.newtype_variant()?,
+        })
+    }
+
+    fn visit_newtype_struct<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_any(self)
+    }
+
+    fn visit_map<A>(self, map: A) -> Result<Self::Value, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        SectionVisitor.visit_map(map).map(Value::Section)
+    }
+}
+
+struct SectionVisitor;
+
+impl<'de> Visitor<'de> for SectionVisitor {
+    type Value = Section;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "an epee section")
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        let mut section = Section::new();
+        while let Some((name, value)) = map.next_entry()? {
+            section.push(name, value);
+        }
+
+        Ok(section)
+    }
+}
+
+struct WithUnknownFieldsVisitor<T>(PhantomData<T>);
+
+impl<'de, T> Visitor<'de> for WithUnknownFieldsVisitor<T>
+where
+    T: Deserialize<'de>,
+{
+    type Value = WithUnknownFields<T>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "an epee section")
+    }
+
+    // the type followed by the fields it ignored, see `deserialize_newtype_struct` of the
+    // deserializer
+    fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        let known = seq
+            .next_element()?
+            .ok_or_else(|| serde::de::Error::invalid_length(0, &self))?;
+        let unknown = seq
+            .next_element()?
+            .ok_or_else(|| serde::de::Error::invalid_length(1, &self))?;
+
+        Ok(WithUnknownFields { known, unknown })
+    }
+
+    fn visit_newtype_struct<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        Ok(WithUnknownFields {
+            known: T::deserialize(deserializer)?,
+            unknown: UnknownFields::new(),
+        })
+    }
+}
+
+/// Serializes a struct or map with the unknown fields appended to its own fields.
+struct FieldsSerializer<'a, S> {
+    serializer: S,
+    unknown: &'a UnknownFields,
+}
+
+impl<'a, S> FieldsSerializer<'a, S>
+where
+    S: Serializer,
+{
+    fn fields(self, len: Option<usize>) -> Result<Fields<'a, S::SerializeMap>, S::Error> {
+        let len = len.map(|len| len + self.unknown.len());
+
+        Ok(Fields {
+            map: self.serializer.serialize_map(len)?,
+            unknown: self.unknown,
+        })
+    }
+}
+
+fn not_a_struct<E>() -> E
+where
+    E: serde::ser::Error,
+{
+    E::custom("Only structs and maps can have unknown fields")
+}
+
+/// Implements `serialize_*` methods for values that can't have unknown fields.
+macro_rules! not_a_struct {
+    ($($method:ident($($ty:ty),*),)*) => {
+        $(
+            fn $method(self, $(_: $ty),*) -> Result<Self::Ok, Self::Error> {
+                Err(not_a_struct())
+            }
+        )*
+    };
+}
+
+impl<'a, S> Serializer for FieldsSerializer<'a, S>
+where
+    S: Serializer,
+{
+    type Ok = S::Ok;
+    type Error = S::Error;
+    type SerializeSeq = Impossible<S::Ok, S::Error>;
+    type SerializeTuple = Impossible<S::Ok, S::Error>;
+    type SerializeTupleStruct = Impossible<S::Ok, S::Error>;
+    type SerializeTupleVariant = Impossible<S::Ok, S::Error>;
+    type SerializeMap = Fields<'a, S::SerializeMap>;
+    type SerializeStruct = Fields<'a, S::SerializeMap>;
+    type SerializeStructVariant = Impossible<S::Ok, S::Error>;
+
+    not_a_struct! {
+        serialize_bool(bool),
+        serialize_i8(i8),
+        serialize_i16(i16),
+        serialize_i32(i32),
+        serialize_i64(i64),
+        serialize_u8(u8),
+        serialize_u16(u16),
+        serialize_u32(u32),
+        serialize_u64(u64),
+        serialize_f32(f32),
+        serialize_f64(f64),
+        serialize_char(char),
+        serialize_str(&str),
+        serialize_bytes(&[u8]),
+        serialize_none(),
+        serialize_unit(),
+        serialize_unit_struct(&'static str),
+        serialize_unit_variant(&'static str, u32, &'static str),
+    }
+
+    fn serialize_some<T>(self, value: &T) -> Result<Self::Ok, Self::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_struct<T>(
+        self,
+        _: &'static str,
+        value: &T,
+    ) -> Result<Self::Ok, Self::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T>(
+        self,
+        _: &'static str,
+        _: u32,
+        _: &'static str,
+        _: &T,
+    ) -> Result<Self::Ok, Self::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        Err(not_a_struct())
+    }
+
+    fn serialize_seq(self, _: Option<usize>) -> Result<Self::SerializeSeq, Self::Error> {
+        Err(not_a_struct())
+    }
+
+    fn serialize_tuple(self, _: usize) -> Result<Self::SerializeTuple, Self::Error> {
+        Err(not_a_struct())
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _: &'static str,
+        _: usize,
+    ) -> Result<Self::SerializeTupleStruct, Self::Error> {
+        Err(not_a_struct())
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        _: &'static str,
+        _: usize,
+    ) -> Result<Self::SerializeTupleVariant, Self::Error> {
+        Err(not_a_struct())
+    }
+
+    fn serialize_map(self, len: Option<usize>) -> Result<Self::SerializeMap, Self::Error> {
+        self.fields(len)
+    }
+
+    fn serialize_struct(
+        self,
+        _: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeStruct, Self::Error> {
+        self.fields(Some(len))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        _: &'static str,
+        _: usize,
+    ) -> Result<Self::SerializeStructVariant, Self::Error> {
+        Err(not_a_struct())
+    }
+
+    fn is_human_readable(&self) -> bool {
+        self.serializer.is_human_readable()
+    }
+}
+
+/// The fields of a struct or map, which are followed by the unknown fields.
+struct Fields<'a, M> {
+    map: M,
+    unknown: &'a UnknownFields,
+}
+
+impl<M> SerializeMap for Fields<'_, M>
+where
+    M: SerializeMap,
+{
+    type Ok = M::Ok;
+    type Error = M::Error;
+
+    fn serialize_key<T>(&mut self, key: &T) -> Result<(), Self::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.map.serialize_key(key)
+    }
+
+    fn serialize_value<T>(&mut self, value: &T) -> Result<(), Self::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.map.serialize_value(value)
+    }
+
+    fn end(mut self) -> Result<Self::Ok, Self::Error> {
+        for (name, value) in &self.unknown.fields {
+            self.map.serialize_entry(name, value)?;
+        }
+
+        self.map.end()
+    }
+}
+
+impl<M> SerializeStruct for Fields<'_, M>
+where
+    M: SerializeMap,
+{
+    type Ok = M::Ok;
+    type Error = M::Error;
+
+    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<(), Self::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.map.serialize_entry(key, value)
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        SerializeMap::end(self)
+    }
+}
+
+/// Provides an owned [`Value`] to a type like the deserializer provides the value it was read
+/// from.
+pub(crate) struct ValueDeserializer(pub(crate) Value);
+
+impl<'de> IntoDeserializer<'de, Error> for ValueDeserializer {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self::Deserializer {
+        self
+    }
+}
+
+impl<'de> Deserializer<'de> for ValueDeserializer {
+    type Error = Error;
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, Error>
+    where
+        V: Visitor<'de>,
+    {
+        match self.0 {
+            Value::I64(v) => visitor.visit_i64(v),
+            Value::I32(v) => visitor.visit_i32(v),
+            Value::I16(v) => visitor.visit_i16(v),
+            Value::I8(v) => visitor.visit_i8(v),
+            Value::U64(v) => visitor.visit_u64(v),
+            Value::U32(v) => visitor.visit_u32(v),
+            Value::U16(v) => visitor.visit_u16(v),
+            Value::U8(v) => visitor.visit_u8(v),
+            Value::F64(v) => visitor.visit_f64(v),
+            Value::String(v) => visitor.visit_byte_buf(v),
+            Value::Bool(v) => visitor.visit_bool(v),
+            Value::Section(section) => {
+                let fields = section
+                    .into_iter()
+                    .map(|(name, value)| (name, ValueDeserializer(value)));
+                let mut map = MapDeserializer::new(fields);
+                let value = visitor.visit_map(&mut map)?;
+                map.end()?;
+
+                Ok(value)
+            }
+            Value::Array { values, .. } => {
+                let mut seq = SeqDeserializer::new(values.into_iter().map(ValueDeserializer));
+                let value = visitor.visit_seq(&mut seq)?;
+                seq.end()?;
+
+                Ok(value)
+            }
+        }
+    }
+
+    fn deserialize_newtype_struct<V>(
+        self,
+        name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error>
+    where
+        V: Visitor<'de>,
+    {
+        match self.0 {
+            Value::Array { element, values } if name == TOKEN => {
+                visitor.visit_enum(TypedArray { element, values })
+            }
+            value if name == TOKEN => ValueDeserializer(value).deserialize_any(visitor),
+            value => visitor.visit_newtype_struct(ValueDeserializer(value)),
+        }
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf option unit unit_struct seq tuple
+        tuple_struct map struct enum identifier ignored_any
+    }
+
+    fn is_human_readable(&self) -> bool {
+        false
+    }
+}
+
+/// Provides an array as a newtype variant whose index is the marker of its elements, like the
+/// deserializer does.
+struct TypedArray {
+    element: ElementType,
+    values: Vec<Value>,
+}
+
+impl<'de> EnumAccess<'de> for TypedArray {
+    type Error = Error;
+    type Variant = Self;
+
+    fn variant_seed<V>(self, seed: V) -> Result<(V::Value, Self::Variant), Error>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        let element = self.element.marker().to_byte();
+        let element = seed.deserialize(IntoDeserializer::<Error>::into_deserializer(element))?;
+
+        Ok((element, self))
+    }
+}
+
+impl<'de> VariantAccess<'de> for TypedArray {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn newtype_variant_seed<T>(self, seed: T) -> Result<T::Value, Error>
+    where
+        T: DeserializeSeed<'de>,
+    {
+        seed.deserialize(ValueDeserializer(Value::Array {
+            element: self.element,
+            values: self.values,
+        }))
+    }
+
+    fn tuple_variant<V>(self, _: usize, visitor: V) -> Result<V::Value, Error>
+    where
+        V: Visitor<'de>,
+    {
+        Err(serde::de::Error::invalid_type(
+            serde::de::Unexpected::NewtypeVariant,
+            &visitor,
+        ))
+    }
+
+    fn struct_variant<V>(self, _: &'static [&'static str], visitor: V) -> Result<V::Value, Error>
+    where
+        V: Visitor<'de>,
+    {
+        Err(serde::de::Error::invalid_type(
+            serde::de::Unexpected::NewtypeVariant,
+            &visitor,
+        ))
+    }
+}
@@ -31,7 +31,10 @@ pub fn encode(number: usize) -> Vec<u8> {
 }
 
 pub fn decode(stream: &mut impl io::BufRead) -> Result<usize, io::Error> {
-    let v = stream.fill_buf()?[0];
+    let v = *stream
+        .fill_buf()?
+        .first()
+        .ok_or(io::ErrorKind::UnexpectedEof)?;
 
     let mask = v & 0x03;
 
@@ -71,7 +74,7 @@ mod tests {
         ];
 
         for (number, expected) in test_cases {
-            let actual = hex::encode(&encode(*number));
+            let actual = hex::encode(encode(*number));
 
             assert_eq!(&actual, expected)
         }
@@ -0,0 +1,15 @@
+use monero_epee_bin_serde::{from_bytes, to_bytes};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::fmt::Debug;
+
+/// Asserts that `value` is unchanged after serializing and deserializing it.
+pub fn round_trip<T>(value: &T)
+where
+    T: Serialize + DeserializeOwned + PartialEq + Debug,
+{
+    let buf = to_bytes(value).unwrap();
+    let value2 = from_bytes::<T, _>(buf).unwrap();
+
+    assert_eq!(value, &value2);
+}
@@ -0,0 +1,379 @@
+use monero_epee_bin_serde::{from_bytes, to_bytes, CharEncoding, Config, StringPolicy};
+use serde::{Deserialize, Serialize};
+use serde_with::serde_as;
+use std::collections::BTreeMap;
+
+#[derive(Debug, Deserialize, Serialize, PartialEq)]
+struct TestF32 {
+    single: f32,
+    many: Vec<f32>,
+}
+
+#[derive(Debug, Deserialize, Serialize, PartialEq)]
+struct TestF64 {
+    single: f64,
+    many: Vec<f64>,
+}
+
+#[test]
+fn f32_is_rejected_by_default() {
+    let val = TestF32 {
+        single: 1.5,
+        many: vec![0.25],
+    };
+    to_bytes(&val).unwrap_err();
+
+    let buf = to_bytes(&TestF64 {
+        single: 1.5,
+        many: vec![0.25],
+    })
+    .unwrap();
+    from_bytes::<TestF32, _>(buf).unwrap_err();
+}
+
+#[test]
+fn widened_f32_is_encoded_as_f64() {
+    let config = Config::new().widen_f32(true);
+    let val = TestF32 {
+        single: 1.5,
+        many: vec![0.25, f32::MAX, f32::NEG_INFINITY],
+    };
+
+    let buf = config.to_bytes(&val).unwrap();
+    assert_eq!(
+        from_bytes::<TestF64, _>(&buf).unwrap(),
+        TestF64 {
+            single: 1.5,
+            many: vec![0.25, f32::MAX.into(), f64::NEG_INFINITY],
+        }
+    );
+
+    let val2 = config.from_bytes(buf).unwrap();
+    assert_eq!(val, val2);
+}
+
+#[test]
+fn narrowing_lossy_f64_to_f32_fails() {
+    let config = Config::new().widen_f32(true);
+
+    for value in [0.1, f64::MAX, f64::from(f32::MIN_POSITIVE) / 3.0] {
+        let buf = to_bytes(&TestF64 {
+            single: value,
+            many: vec![],
+        })
+        .unwrap();
+
+        config.from_bytes::<TestF32, _>(buf).unwrap_err();
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, PartialEq)]
+struct TestChar {
+    c: char,
+}
+
+#[test]
+fn char_is_a_utf8_string() {
+    let val = TestChar { c: 'ɱ' };
+
+    let buf = to_bytes(&val).unwrap();
+    assert_eq!(&buf[9..], b"\x04\x01c\x0a\x08\xc9\xb1");
+
+    let val2 = from_bytes(buf).unwrap();
+    assert_eq!(val, val2);
+}
+
+#[test]
+fn char_from_string_must_be_single_character() {
+    #[derive(Serialize)]
+    struct Test {
+        c: &'static str,
+    }
+
+    for c in ["", "ab"] {
+        let buf = to_bytes(&Test { c }).unwrap();
+        from_bytes::<TestChar, _>(buf).unwrap_err();
+    }
+}
+
+#[test]
+fn char_must_be_utf8() {
+    let buf = b"\x01\x11\x01\x01\x01\x01\x02\x01\x01\x04\x01c\x0a\x04\xff";
+
+    let err = from_bytes::<TestChar, _>(buf).unwrap_err();
+    assert_eq!(err.to_string(), "String contained non-UTF-8 characters");
+}
+
+#[test]
+fn legacy_char_is_a_single_byte() {
+    let config = Config::new().char_encoding(CharEncoding::Byte);
+    let val = TestChar { c: 'ÿ' };
+
+    let buf = config.to_bytes(&val).unwrap();
+    assert_eq!(&buf[9..], b"\x04\x01c\x08\xff");
+
+    let val2 = config.from_bytes(buf).unwrap();
+    assert_eq!(val, val2);
+
+    config.to_bytes(&TestChar { c: 'ɱ' }).unwrap_err();
+}
+
+#[derive(Debug, Deserialize, Serialize, PartialEq)]
+struct Section {
+    number: u32,
+}
+
+#[test]
+fn headerless_round_trip() {
+    let config = Config::new().header(false);
+    let val = Section { number: 7 };
+
+    let with_header = to_bytes(&val).unwrap();
+    let without_header = config.to_bytes(&val).unwrap();
+    assert_eq!(without_header, with_header[9..]);
+    assert_eq!(config.serialized_size(&val).unwrap(), without_header.len());
+
+    assert_eq!(
+        config.from_bytes::<Section, _>(&without_header).unwrap(),
+        val
+    );
+    from_bytes::<Section, _>(&without_header).unwrap_err();
+}
+
+#[test]
+fn header_mismatches_are_reported_separately() {
+    let bytes = to_bytes(&Section { number: 7 }).unwrap();
+
+    let mut signature_a = bytes.clone();
+    signature_a[1] = 0x12;
+    let err = from_bytes::<Section, _>(&signature_a).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Expected header signature A 0x01011101 but found 0x01011201"
+    );
+
+    let mut signature_b = bytes.clone();
+    signature_b[6] = 0x03;
+    let err = from_bytes::<Section, _>(&signature_b).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Expected header signature B 0x01020101 but found 0x01030101"
+    );
+
+    let err = from_bytes::<Section, _>(&bytes[..6]).unwrap_err();
+    assert_eq!(err.to_string(), "Missing magic header bytes");
+}
+
+#[test]
+fn future_format_versions_can_be_accepted() {
+    let mut bytes = to_bytes(&Section { number: 7 }).unwrap();
+    bytes[8] = 2;
+
+    let err = from_bytes::<Section, _>(&bytes).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Format version 2 is not supported, expected a version between 1 and 1"
+    );
+
+    let config = Config::new().max_format_version(2);
+    assert_eq!(
+        config.from_bytes::<Section, _>(&bytes).unwrap(),
+        Section { number: 7 }
+    );
+
+    bytes[8] = 0;
+    config.from_bytes::<Section, _>(&bytes).unwrap_err();
+}
+
+#[derive(Debug, Deserialize, Serialize, PartialEq)]
+struct Narrow {
+    number: u32,
+    numbers: Vec<u32>,
+}
+
+#[derive(Debug, Deserialize, Serialize, PartialEq)]
+struct Wide {
+    number: u64,
+    numbers: Vec<u64>,
+}
+
+#[test]
+fn strict_mode_requires_exact_markers() {
+    let narrow = to_bytes(&Narrow {
+        number: 1,
+        numbers: vec![2, 3],
+    })
+    .unwrap();
+    let wide = Wide {
+        number: 1,
+        numbers: vec![2, 3],
+    };
+
+    assert_eq!(from_bytes::<Wide, _>(&narrow).unwrap(), wide);
+
+    let strict = Config::new().strict(true);
+    let err = strict.from_bytes::<Wide, _>(&narrow).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Expected marker Single(5) but found Single(6)"
+    );
+
+    let bytes = to_bytes(&wide).unwrap();
+    assert_eq!(strict.from_bytes::<Wide, _>(&bytes).unwrap(), wide);
+}
+
+#[derive(Debug, Deserialize, Serialize, PartialEq)]
+struct Numbers<T> {
+    numbers: Vec<T>,
+}
+
+#[test]
+fn strict_mode_checks_array_elements() {
+    let bytes = to_bytes(&Numbers::<u32> {
+        numbers: vec![2, 3],
+    })
+    .unwrap();
+
+    from_bytes::<Numbers<u64>, _>(&bytes).unwrap();
+    let err = Config::new()
+        .strict(true)
+        .from_bytes::<Numbers<u64>, _>(&bytes)
+        .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Expected marker Single(5) but found Single(6)"
+    );
+}
+
+#[test]
+fn strict_mode_checks_sequences() {
+    let bytes = to_bytes(&Narrow {
+        number: 1,
+        numbers: vec![2],
+    })
+    .unwrap();
+
+    #[derive(Debug, Deserialize)]
+    #[allow(dead_code)]
+    struct Swapped {
+        number: Vec<u32>,
+        numbers: Vec<u32>,
+    }
+
+    let err = Config::new()
+        .strict(true)
+        .from_bytes::<Swapped, _>(&bytes)
+        .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Expected an array or a blob but found Single(6)"
+    );
+}
+
+#[test]
+fn strict_mode_checks_sections() {
+    #[derive(Debug, Deserialize, Serialize, PartialEq)]
+    struct Inner {
+        number: u64,
+    }
+
+    #[derive(Debug, Deserialize, Serialize, PartialEq)]
+    struct Outer<T> {
+        inner: T,
+    }
+
+    // structs also accept the values of their fields in order from an array
+    let bytes = to_bytes(&Outer { inner: vec![1u64] }).unwrap();
+    assert_eq!(
+        from_bytes::<Outer<Inner>, _>(&bytes).unwrap(),
+        Outer {
+            inner: Inner { number: 1 }
+        }
+    );
+
+    let err = Config::new()
+        .strict(true)
+        .from_bytes::<Outer<Inner>, _>(&bytes)
+        .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Expected marker Single(c) but found Sequence(5)"
+    );
+
+    let bytes = to_bytes(&Outer {
+        inner: Inner { number: 1 },
+    })
+    .unwrap();
+    let strict = Config::new().strict(true);
+    strict.from_bytes::<Outer<Inner>, _>(&bytes).unwrap();
+    strict
+        .from_bytes::<Outer<BTreeMap<String, u64>>, _>(&bytes)
+        .unwrap();
+}
+
+#[derive(Debug, Deserialize, Serialize, PartialEq)]
+struct Strings {
+    text: String,
+    blob: Vec<u8>,
+}
+
+#[serde_as]
+#[derive(Debug, Deserialize, PartialEq)]
+#[serde(untagged)]
+enum TextOrBlob {
+    Text(String),
+    Blob(#[serde_as(as = "serde_with::Bytes")] Vec<u8>),
+}
+
+#[derive(Debug, Deserialize, PartialEq)]
+struct Untagged {
+    text: TextOrBlob,
+    blob: TextOrBlob,
+}
+
+fn strings() -> Vec<u8> {
+    to_bytes(&Strings {
+        text: "epee".to_owned(),
+        blob: vec![0xff, 0xfe],
+    })
+    .unwrap()
+}
+
+#[test]
+fn strings_are_bytes_by_default() {
+    let err = from_bytes::<serde_json::Value, _>(strings()).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "invalid type: byte array, expected any valid JSON value"
+    );
+}
+
+#[test]
+fn utf8_strings_can_be_text() {
+    let config = Config::new().string_policy(StringPolicy::Utf8ThenBytes);
+
+    let untagged: Untagged = config.from_bytes(strings()).unwrap();
+    assert_eq!(untagged.text, TextOrBlob::Text("epee".to_owned()));
+    assert_eq!(untagged.blob, TextOrBlob::Blob(vec![0xff, 0xfe]));
+
+    let untagged: Untagged = config.from_reader(strings().as_slice()).unwrap();
+    assert_eq!(untagged.text, TextOrBlob::Text("epee".to_owned()));
+    assert_eq!(untagged.blob, TextOrBlob::Blob(vec![0xff, 0xfe]));
+
+    let typed: Strings = config.from_bytes(strings()).unwrap();
+    assert_eq!(typed.blob, vec![0xff, 0xfe]);
+}
+
+#[test]
+fn strings_can_be_lossy_text() {
+    let config = Config::new().string_policy(StringPolicy::Lossy);
+
+    let value: serde_json::Value = config.from_bytes(strings()).unwrap();
+    assert_eq!(
+        value,
+        serde_json::json!({"text": "epee", "blob": "\u{fffd}\u{fffd}"})
+    );
+
+    let typed: Strings = config.from_bytes(strings()).unwrap();
+    assert_eq!(typed.blob, vec![0xff, 0xfe]);
+}
@@ -0,0 +1,182 @@
+mod common;
+
+use common::round_trip;
+use monero_epee_bin_serde::{from_bytes, to_bytes};
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+enum External {
+    Unit,
+    Newtype(u64),
+    Tuple(u32, u32),
+    Struct { height: u64, hash: [u8; 4] },
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+struct Wrapper {
+    message: External,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+#[serde(tag = "type")]
+enum Internal {
+    Ping,
+    Chain { height: u64, status: String },
+    Peer(Peer),
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+struct Peer {
+    id: u64,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+struct InternalWrapper {
+    first: Internal,
+    second: Internal,
+}
+
+#[test]
+fn unit_variant_is_a_string() {
+    let buf = to_bytes(&Wrapper {
+        message: External::Unit,
+    })
+    .unwrap();
+
+    assert_eq!(&buf[9..], b"\x04\x07message\x0a\x10Unit");
+}
+
+#[test]
+fn newtype_variant_is_a_section_with_one_field() {
+    let buf = to_bytes(&Wrapper {
+        message: External::Newtype(1),
+    })
+    .unwrap();
+
+    assert_eq!(
+        &buf[9..],
+        b"\x04\x07message\x0c\x04\x07Newtype\x05\x01\x00\x00\x00\x00\x00\x00\x00"
+    );
+}
+
+#[test]
+fn externally_tagged_enums_round_trip() {
+    for message in [
+        External::Unit,
+        External::Newtype(u64::MAX),
+        External::Tuple(1, 2),
+        External::Struct {
+            height: 100,
+            hash: [1, 2, 3, 4],
+        },
+    ] {
+        round_trip(&Wrapper {
+            message: message.clone(),
+        });
+    }
+}
+
+#[test]
+fn sequence_of_enums() {
+    #[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+    struct Test {
+        messages: Vec<External>,
+    }
+
+    round_trip(&Test {
+        messages: vec![External::Newtype(1), External::Tuple(2, 3)],
+    });
+}
+
+#[test]
+fn enum_can_be_root_unless_unit() {
+    round_trip(&External::Struct {
+        height: 100,
+        hash: [1, 2, 3, 4],
+    });
+    round_trip(&External::Newtype(1));
+
+    to_bytes(&External::Unit).unwrap_err();
+}
+
+#[test]
+fn internally_tagged_enums_round_trip() {
+    round_trip(&InternalWrapper {
+        first: Internal::Ping,
+        second: Internal::Chain {
+            height: 1,
+            status: "OK".to_owned(),
+        },
+    });
+    round_trip(&InternalWrapper {
+        first: Internal::Peer(Peer { id: 7 }),
+        second: Internal::Ping,
+    });
+}
+
+#[test]
+fn unknown_variant_fails() {
+    #[derive(Serialize)]
+    struct Other {
+        message: &'static str,
+    }
+
+    let buf = to_bytes(&Other { message: "Foo" }).unwrap();
+    from_bytes::<Wrapper, _>(buf).unwrap_err();
+}
+
+monero_epee_bin_serde::repr_enum! {
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    pub enum SupportFlags: u32 {
+        None = 0,
+        Fluffy = 1,
+    }
+}
+
+monero_epee_bin_serde::repr_enum! {
+    /// Offset discriminants to make sure they are not confused with variant indices.
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    enum Signed: i16 {
+        Negative = -10,
+        Positive = 10,
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+struct NodeData {
+    support_flags: SupportFlags,
+    signed: Vec<Signed>,
+}
+
+#[test]
+fn repr_enums_are_integers() {
+    let node_data = NodeData {
+        support_flags: SupportFlags::Fluffy,
+        signed: vec![Signed::Positive, Signed::Negative],
+    };
+
+    let buf = to_bytes(&node_data).unwrap();
+    assert_eq!(
+        &buf[9..],
+        b"\x08\x0dsupport_flags\x06\x01\x00\x00\x00\x06signed\x83\x08\x0a\x00\xf6\xff"
+    );
+
+    round_trip(&node_data);
+}
+
+#[test]
+fn unknown_discriminant_fails() {
+    #[derive(Serialize)]
+    struct Raw {
+        support_flags: u32,
+        signed: Vec<i16>,
+    }
+
+    let buf = to_bytes(&Raw {
+        support_flags: 2,
+        signed: vec![10],
+    })
+    .unwrap();
+    let err = from_bytes::<NodeData, _>(buf).unwrap_err();
+    assert_eq!(err.to_string(), "unknown discriminant 2 for SupportFlags");
+}
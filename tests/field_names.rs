@@ -0,0 +1,97 @@
+use monero_epee_bin_serde::{from_bytes, to_bytes};
+use rand::distributions::{Distribution, Standard};
+use rand::{random, thread_rng, Rng};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+#[derive(Debug, Deserialize, Serialize, PartialEq)]
+struct Test {
+    #[serde(flatten)]
+    fields: BTreeMap<String, u8>,
+}
+
+#[test]
+fn field_name_length_is_validated_when_serializing() {
+    let mut rng = thread_rng();
+
+    for _ in 0..1000 {
+        let length = rng.gen_range(0..300);
+        let key = Distribution::<char>::sample_iter(Standard, &mut rng)
+            .take(length)
+            .collect::<String>();
+        let val = Test {
+            fields: BTreeMap::from([(key.clone(), random())]),
+        };
+
+        let result = to_bytes(&val);
+
+        if key.len() > 254 {
+            result.unwrap_err();
+            continue;
+        }
+
+        let val2 = from_bytes(result.unwrap()).unwrap();
+        assert_eq!(val, val2);
+    }
+}
+
+#[test]
+fn field_name_is_validated_when_deserializing() {
+    let mut rng = thread_rng();
+
+    for _ in 0..1000 {
+        let length = rng.gen_range(0..=255);
+        let mut name = (0..length).map(|_| rng.gen::<u8>()).collect::<Vec<_>>();
+        if rng.gen() {
+            name.iter_mut().for_each(|byte| *byte &= 0x7f);
+        }
+
+        let mut buf = b"\x01\x11\x01\x01\x01\x01\x02\x01\x01\x04".to_vec();
+        buf.push(length as u8);
+        buf.extend_from_slice(&name);
+        buf.extend_from_slice(&[0x08, 0x01]);
+
+        let result = from_bytes::<Test, _>(buf);
+
+        if length > 254 {
+            result.unwrap_err();
+            continue;
+        }
+
+        match String::from_utf8(name) {
+            Ok(name) => {
+                assert_eq!(result.unwrap().fields, BTreeMap::from([(name, 1)]))
+            }
+            _ => {
+                result.unwrap_err();
+            }
+        }
+    }
+}
+
+#[test]
+fn long_field_name_is_rejected() {
+    #[derive(Serialize)]
+    struct Test {
+        #[serde(
+            rename = "aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa"
+        )]
+        field: u8,
+    }
+
+    let err = to_bytes(&Test { field: 1 }).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Fieldname must be at most 254 bytes long but is 255 bytes long"
+    );
+
+    let mut buf = b"\x01\x11\x01\x01\x01\x01\x02\x01\x01\x04\xff".to_vec();
+    buf.extend_from_slice(&[b'a'; 255]);
+    buf.extend_from_slice(&[0x08, 0x01]);
+
+    let err = from_bytes::<BTreeMap<String, u8>, _>(buf).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Fieldname must be at most 254 bytes long but is 255 bytes long"
+    );
+}
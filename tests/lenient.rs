@@ -0,0 +1,177 @@
+use monero_epee_bin_serde::value::{Section, Value};
+use monero_epee_bin_serde::{from_bytes, to_bytes, Config};
+use serde::Serialize;
+
+#[derive(Serialize)]
+struct Peer {
+    id: u64,
+    alive: bool,
+}
+
+#[derive(Serialize)]
+struct PeerList {
+    height: u64,
+    peers: Vec<Peer>,
+    top_id: [u8; 4],
+}
+
+fn peer_list() -> Vec<u8> {
+    to_bytes(&PeerList {
+        height: 100,
+        peers: vec![
+            Peer { id: 1, alive: true },
+            Peer {
+                id: 2,
+                alive: false,
+            },
+        ],
+        top_id: [1, 2, 3, 4],
+    })
+    .unwrap()
+}
+
+#[test]
+fn complete_input() {
+    let bytes = peer_list();
+
+    let partial = Config::new().from_slice_lenient(&bytes);
+
+    assert!(partial.is_complete());
+    assert_eq!(partial.section, from_bytes::<Section, _>(&bytes).unwrap());
+}
+
+#[test]
+fn truncated_input_keeps_parsed_values() {
+    let bytes = peer_list();
+    // cut off the `alive` field of the second peer
+    let end = bytes.len() - b"\x06top_id\x0a\x10\x01\x02\x03\x04".len() - 1;
+
+    let partial = Config::new().from_slice_lenient(&bytes[..end]);
+
+    assert_eq!(partial.errors.len(), 1);
+    assert_eq!(partial.errors[0].0, end);
+    assert_eq!(partial.section.get("height"), Some(&Value::U64(100)));
+
+    let peers = match partial.section.get("peers") {
+        Some(Value::Array { values, .. }) => values,
+        other => panic!("unexpected peers {:?}", other),
+    };
+    assert_eq!(peers.len(), 2);
+    let second = match &peers[1] {
+        Value::Section(second) => second,
+        other => panic!("unexpected peer {:?}", other),
+    };
+    assert_eq!(second.get("id"), Some(&Value::U64(2)));
+    assert_eq!(second.get("alive"), None);
+    assert_eq!(partial.section.get("top_id"), None);
+}
+
+#[test]
+fn recoverable_errors_are_collected() {
+    let mut bytes = peer_list();
+    bytes[1] = 0x12;
+    let alive = bytes.windows(6).position(|w| w == b"alive\x0b").unwrap() + 6;
+    bytes[alive] = 2;
+
+    let partial = Config::new().from_slice_lenient(&bytes);
+
+    let offsets = partial
+        .errors
+        .iter()
+        .map(|(offset, _)| *offset)
+        .collect::<Vec<_>>();
+    assert_eq!(offsets, vec![0, alive]);
+    assert!(partial.section.get("top_id").is_some());
+}
+
+#[test]
+fn unknown_marker_stops_parsing() {
+    let mut bytes = peer_list();
+    let marker = bytes.windows(7).position(|w| w == b"top_id\x0a").unwrap() + 6;
+    bytes[marker] = 0x0d;
+
+    let partial = Config::new().from_slice_lenient(&bytes);
+
+    assert_eq!(partial.errors.len(), 1);
+    assert_eq!(partial.errors[0].0, marker);
+    assert_eq!(
+        partial.errors[0].1.to_string(),
+        "Unknown marker value Single(d)"
+    );
+    assert!(partial.section.get("peers").is_some());
+}
+
+#[test]
+fn deep_nesting_stops_parsing() {
+    let mut bytes = b"\x01\x11\x01\x01\x01\x01\x02\x01\x01\x04".to_vec();
+    for _ in 0..1000 {
+        bytes.extend_from_slice(b"\x01a\x0c\x04");
+    }
+
+    let partial = Config::new().from_slice_lenient(&bytes);
+
+    assert_eq!(partial.errors.len(), 1);
+    assert_eq!(
+        partial.errors[0].1.to_string(),
+        "Sections and arrays must not be nested more than 100 levels deep"
+    );
+}
+
+#[test]
+fn nesting_limit_counts_the_root_section() {
+    // sections nested `levels` deep, counting the root section
+    let nested = |levels| {
+        let mut bytes = b"\x01\x11\x01\x01\x01\x01\x02\x01\x01\x04".to_vec();
+        for _ in 1..levels {
+            bytes.extend_from_slice(b"\x01a\x0c\x04");
+        }
+        bytes.extend_from_slice(b"\x01a\x08\x01");
+
+        bytes
+    };
+
+    let bytes = nested(100);
+    let partial = Config::new().from_slice_lenient(&bytes);
+    assert!(partial.is_complete());
+    assert_eq!(partial.section, from_bytes::<Section, _>(&bytes).unwrap());
+
+    let bytes = nested(101);
+    let partial = Config::new().from_slice_lenient(&bytes);
+    assert_eq!(partial.errors.len(), 1);
+    assert_eq!(
+        partial.errors[0].1.to_string(),
+        "Sections and arrays must not be nested more than 100 levels deep"
+    );
+    from_bytes::<Section, _>(&bytes).unwrap_err();
+}
+
+#[test]
+fn duplicate_fields_are_kept() {
+    let bytes = b"\x01\x11\x01\x01\x01\x01\x02\x01\x01\x08\x01a\x08\x01\x01a\x08\x02";
+
+    let partial = Config::new().from_slice_lenient(bytes);
+
+    assert!(partial.is_complete());
+    assert_eq!(
+        partial.section.iter().collect::<Vec<_>>(),
+        [("a", &Value::U8(1)), ("a", &Value::U8(2))]
+    );
+    assert_eq!(partial.section, from_bytes::<Section, _>(bytes).unwrap());
+}
+
+#[test]
+fn long_field_names_are_reported() {
+    let mut bytes = b"\x01\x11\x01\x01\x01\x01\x02\x01\x01\x08\xff".to_vec();
+    bytes.extend_from_slice(&[b'a'; 255]);
+    bytes.extend_from_slice(b"\x08\x01\x01b\x08\x02");
+
+    let partial = Config::new().from_slice_lenient(&bytes);
+
+    assert_eq!(partial.errors.len(), 1);
+    assert_eq!(partial.errors[0].0, 10);
+    assert_eq!(
+        partial.errors[0].1.to_string(),
+        "Fieldname must be at most 254 bytes long but is 255 bytes long"
+    );
+    assert_eq!(partial.section.get("b"), Some(&Value::U8(2)));
+}
@@ -1,5 +1,6 @@
 use hex_literal::hex;
-use monero_epee_bin_serde::{from_bytes, to_bytes};
+use monero_epee_bin_serde::value::Value;
+use monero_epee_bin_serde::{from_bytes, serialized_size, to_bytes};
 use serde::{Deserialize, Serialize};
 use serde_with::serde_as;
 use serde_with::TryFromInto;
@@ -29,6 +30,27 @@ pub struct CoreSyncData {
     pub top_version: u8,
 }
 
+#[serde_as]
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct WideCoreSyncData {
+    #[serde(
+        flatten,
+        with = "monero_epee_bin_serde::u128_pair::cumulative_difficulty"
+    )]
+    pub cumulative_difficulty: u128,
+    pub current_height: u64,
+    pub pruning_seed: u32,
+    #[serde_as(as = "TryFromInto<[u8; 32]>")]
+    pub top_id: monero::Hash,
+    pub top_version: u8,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct WideHandshakeR {
+    pub node_data: BasicNodeData,
+    pub payload_data: WideCoreSyncData,
+}
+
 #[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
 pub struct HandshakeR {
     pub node_data: BasicNodeData,
@@ -65,4 +87,52 @@ fn received_handshake() {
     assert_eq!(decoded_handshake, handshake);
     let encoded_handshake = to_bytes(&handshake).unwrap();
     assert_eq!(encoded_handshake, bytes);
+    assert_eq!(serialized_size(&handshake).unwrap(), bytes.len());
+}
+
+#[test]
+fn received_handshake_round_trips_as_value() {
+    let bytes = hex!("01110101010102010108096e6f64655f646174610c10076d795f706f727406a04600000a6e6574776f726b5f69640a401230f171610441611731008216a1a11007706565725f6964053eb3c096c4471c340d737570706f72745f666c61677306010000000c7061796c6f61645f646174610c181563756d756c61746976655f646966666963756c7479053951f7a79aab4a031b63756d756c61746976655f646966666963756c74795f746f7036340500000000000000000e63757272656e745f68656967687405fa092a00000000000c7072756e696e675f73656564068001000006746f705f69640a806cc497b230ba57a95edb370be8d6870c94e0992937c89b1def3a4cb7726d37ad0b746f705f76657273696f6e0810");
+    let value = from_bytes::<Value, _>(bytes).unwrap();
+
+    assert_eq!(to_bytes(&value).unwrap(), bytes);
+}
+
+#[test]
+fn received_handshake_with_u128_difficulty() {
+    let bytes = hex!("01110101010102010108096e6f64655f646174610c10076d795f706f727406a04600000a6e6574776f726b5f69640a401230f171610441611731008216a1a11007706565725f6964053eb3c096c4471c340d737570706f72745f666c61677306010000000c7061796c6f61645f646174610c181563756d756c61746976655f646966666963756c7479053951f7a79aab4a031b63756d756c61746976655f646966666963756c74795f746f7036340500000000000000000e63757272656e745f68656967687405fa092a00000000000c7072756e696e675f73656564068001000006746f705f69640a806cc497b230ba57a95edb370be8d6870c94e0992937c89b1def3a4cb7726d37ad0b746f705f76657273696f6e0810");
+    let decoded_handshake = from_bytes::<WideHandshakeR, _>(bytes).unwrap();
+
+    assert_eq!(
+        decoded_handshake.payload_data.cumulative_difficulty,
+        237190611121688889
+    );
+
+    let encoded_handshake = to_bytes(&decoded_handshake).unwrap();
+    assert_eq!(encoded_handshake, bytes);
+
+    let mut wide_handshake = decoded_handshake;
+    wide_handshake.payload_data.cumulative_difficulty = u128::MAX - 1;
+    let encoded_handshake = to_bytes(&wide_handshake).unwrap();
+    let decoded_handshake = from_bytes::<HandshakeR, _>(encoded_handshake).unwrap();
+    assert_eq!(
+        decoded_handshake.payload_data.cumulative_difficulty,
+        u64::MAX - 1
+    );
+    assert_eq!(
+        decoded_handshake.payload_data.cumulative_difficulty_top64,
+        u64::MAX
+    );
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+struct PingRequest;
+
+#[test]
+fn ping_request_is_an_empty_section() {
+    let bytes = to_bytes(&PingRequest).unwrap();
+    assert_eq!(bytes, b"\x01\x11\x01\x01\x01\x01\x02\x01\x01\x00");
+
+    assert_eq!(from_bytes::<PingRequest, _>(&bytes).unwrap(), PingRequest);
+    assert_eq!(to_bytes(&()).unwrap(), bytes);
 }
@@ -1,5 +1,11 @@
-use monero_epee_bin_serde::{from_bytes, to_bytes};
+mod common;
+
+use common::round_trip;
+use monero_epee_bin_serde::value::{ElementType, Section, Value};
+use monero_epee_bin_serde::{from_bytes, from_reader, to_bytes, Config};
 use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, VecDeque};
+use std::net::{Ipv4Addr, Ipv6Addr};
 
 #[derive(Default, Deserialize, Serialize, PartialEq, Debug)]
 struct TestSeq {
@@ -34,3 +40,350 @@ fn optional_val_not_present() {
     let val2 = from_bytes(buf).unwrap();
     assert_eq!(val, val2);
 }
+
+#[derive(Default, Deserialize, Serialize, PartialEq, Debug)]
+struct TestMap {
+    peers: BTreeMap<String, u64>,
+    #[serde(flatten)]
+    rest: BTreeMap<String, String>,
+}
+
+#[test]
+fn maps_are_sections() {
+    let mut val = TestMap::default();
+    val.peers.insert("alice".to_owned(), 1);
+    val.peers.insert("bob".to_owned(), 2);
+    val.rest.insert("status".to_owned(), "OK".to_owned());
+
+    let buf = to_bytes(&val).unwrap();
+    assert_eq!(
+        &buf[9..],
+        b"\x08\x05peers\x0c\x08\x05alice\x05\x01\x00\x00\x00\x00\x00\x00\x00\x03bob\x05\x02\x00\x00\x00\x00\x00\x00\x00\x06status\x0a\x08OK"
+    );
+
+    let val2 = from_bytes(buf).unwrap();
+    assert_eq!(val, val2);
+}
+
+#[test]
+fn sequence_of_maps() {
+    #[derive(Deserialize, Serialize, PartialEq, Debug)]
+    struct Test {
+        maps: Vec<BTreeMap<String, u8>>,
+    }
+
+    let val = Test {
+        maps: vec![
+            BTreeMap::from([("a".to_owned(), 1)]),
+            BTreeMap::from([("b".to_owned(), 2), ("c".to_owned(), 3)]),
+        ],
+    };
+
+    let buf = to_bytes(&val).unwrap();
+    let val2 = from_bytes(buf).unwrap();
+    assert_eq!(val, val2);
+}
+
+#[test]
+fn map_keys_must_be_strings() {
+    #[derive(Serialize)]
+    struct Test {
+        map: BTreeMap<u64, u64>,
+    }
+
+    to_bytes(&Test {
+        map: BTreeMap::from([(1, 1)]),
+    })
+    .unwrap_err();
+}
+
+#[test]
+fn u128_is_not_supported() {
+    #[derive(Deserialize, Serialize, Debug)]
+    struct Test {
+        difficulty: u128,
+    }
+
+    let err = to_bytes(&Test { difficulty: 1 }).unwrap_err();
+    assert!(err.to_string().contains("u128_pair"));
+}
+
+#[test]
+fn i128_is_not_supported() {
+    #[derive(Deserialize, Serialize, Debug)]
+    struct Test {
+        balance: i128,
+    }
+
+    let err = to_bytes(&Test { balance: -1 }).unwrap_err();
+    assert_eq!(err.to_string(), "Type i128 is not supported");
+}
+
+#[derive(Default, Deserialize, Serialize, PartialEq, Debug)]
+struct TestByteSequences {
+    vec: Vec<u8>,
+    boxed: Box<[u8]>,
+    deque: VecDeque<u8>,
+}
+
+#[test]
+fn byte_sequences_round_trip_as_blobs() {
+    let obj = TestByteSequences {
+        vec: vec![1, 2, 3],
+        boxed: vec![4, 5].into_boxed_slice(),
+        deque: VecDeque::from(vec![6]),
+    };
+    let data = to_bytes(&obj).unwrap();
+    assert_eq!(obj, from_bytes(&data).unwrap());
+    assert_eq!(obj, from_reader(data.as_slice()).unwrap());
+}
+
+#[test]
+fn empty_byte_sequences_round_trip() {
+    let obj = TestByteSequences::default();
+    let data = to_bytes(&obj).unwrap();
+    assert_eq!(obj, from_bytes(data).unwrap());
+}
+
+#[derive(Debug, Deserialize)]
+struct TestWideSequence {
+    #[allow(dead_code)]
+    vec: Vec<u16>,
+}
+
+#[test]
+fn only_byte_sequences_are_read_from_blobs() {
+    let data = to_bytes(&TestByteSequences {
+        vec: vec![1, 2],
+        ..Default::default()
+    })
+    .unwrap();
+
+    for config in [Config::new(), Config::new().strict(true)] {
+        let err = config.from_bytes::<TestWideSequence, _>(&data).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Expected marker Sequence(7) but found Single(a)"
+        );
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, PartialEq)]
+struct TestNestedSequences {
+    blobs: Vec<Vec<u8>>,
+    arrays: Vec<Vec<u64>>,
+    pair: (Vec<u8>, Vec<u8>),
+}
+
+#[test]
+fn nested_sequences_round_trip() {
+    let obj = TestNestedSequences {
+        blobs: vec![vec![1, 2], vec![], vec![3]],
+        arrays: vec![vec![], vec![4], vec![5, 6]],
+        pair: (vec![7], vec![8, 9]),
+    };
+    round_trip(&obj);
+
+    let data = to_bytes(&obj).unwrap();
+    assert_eq!(
+        &data[9..],
+        b"\x0c\x05blobs\x8a\x0c\x08\x01\x02\x00\x04\x03\x06arrays\x8d\x0c\x85\x00\x85\x04\x04\x00\x00\x00\x00\x00\x00\x00\x85\x08\x05\x00\x00\x00\x00\x00\x00\x00\x06\x00\x00\x00\x00\x00\x00\x00\x04pair\x8a\x08\x04\x07\x08\x08\x09"
+    );
+}
+
+#[test]
+fn empty_nested_sequences_round_trip() {
+    round_trip(&TestNestedSequences {
+        blobs: vec![vec![], vec![1]],
+        arrays: vec![vec![], vec![]],
+        pair: (vec![], vec![]),
+    });
+}
+
+#[test]
+fn sequences_of_nested_sequences_round_trip() {
+    round_trip(&TestSeqOfSeqs {
+        seq: vec![vec![vec![1, 2], vec![]], vec![vec![3]]],
+    });
+}
+
+#[derive(Debug, Deserialize, Serialize, PartialEq)]
+struct TestSeqOfSeqs {
+    seq: Vec<Vec<Vec<u64>>>,
+}
+
+#[derive(Serialize)]
+#[serde(untagged)]
+enum Mixed {
+    Number(u32),
+    Text(String),
+}
+
+#[derive(Serialize)]
+struct TestMixedSeq {
+    seq: Vec<Mixed>,
+}
+
+#[test]
+fn array_elements_must_have_the_same_type() {
+    let obj = TestMixedSeq {
+        seq: vec![Mixed::Number(1), Mixed::Number(2)],
+    };
+    to_bytes(&obj).unwrap();
+
+    let obj = TestMixedSeq {
+        seq: vec![Mixed::Number(1), Mixed::Text("two".to_owned())],
+    };
+    let err = to_bytes(&obj).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Array elements must have the same type, expected Single(6) but got Single(a)"
+    );
+}
+
+#[derive(Debug, Deserialize, Serialize, PartialEq)]
+struct Empty;
+
+#[derive(Debug, Deserialize, Serialize, PartialEq)]
+struct TestUnits {
+    unit: (),
+    empty: Empty,
+    many: Vec<Empty>,
+}
+
+#[test]
+fn units_are_empty_sections() {
+    let obj = TestUnits {
+        unit: (),
+        empty: Empty,
+        many: vec![Empty, Empty],
+    };
+    let data = to_bytes(&obj).unwrap();
+    assert_eq!(
+        &data[9..],
+        b"\x0c\x04unit\x0c\x00\x05empty\x0c\x00\x04many\x8c\x08\x00\x00"
+    );
+    assert_eq!(obj, from_bytes(data).unwrap());
+}
+
+#[test]
+fn units_skip_unknown_fields() {
+    let data = to_bytes(&TestSeq { seq: vec![1, 2] }).unwrap();
+    from_bytes::<Empty, _>(data).unwrap();
+}
+
+/// An element that is made up without reading anything from the deserializer.
+#[derive(Debug, PartialEq)]
+struct Unread;
+
+impl<'de> Deserialize<'de> for Unread {
+    fn deserialize<D>(_: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        Ok(Unread)
+    }
+}
+
+#[derive(Debug, Deserialize, PartialEq)]
+struct TestUnreadElements {
+    unread: Vec<Unread>,
+    after: u8,
+}
+
+#[test]
+fn unread_elements_do_not_affect_the_next_value() {
+    let data = b"\x01\x11\x01\x01\x01\x01\x02\x01\x01\x08\x06unread\x87\x04\x05after\x08\x07";
+
+    let obj = from_bytes::<TestUnreadElements, _>(data).unwrap();
+    assert_eq!(
+        obj,
+        TestUnreadElements {
+            unread: vec![Unread],
+            after: 7
+        }
+    );
+}
+
+#[test]
+fn deep_nesting_is_rejected() {
+    let mut sections = b"\x01\x11\x01\x01\x01\x01\x02\x01\x01\x04".to_vec();
+    let mut arrays = sections.clone();
+    arrays.extend_from_slice(b"\x01a");
+    for _ in 0..10_000 {
+        sections.extend_from_slice(b"\x01a\x0c\x04");
+        arrays.extend_from_slice(b"\x8d\x04");
+    }
+
+    for data in [sections, arrays] {
+        let err = from_bytes::<Value, _>(&data).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Sections and arrays must not be nested more than 100 levels deep"
+        );
+
+        from_bytes::<Empty, _>(&data).unwrap_err();
+    }
+}
+
+/// A section that is nested `levels` deep, counting itself.
+fn nested_sections(levels: usize) -> Value {
+    let mut value = Value::U8(1);
+    for _ in 0..levels {
+        value = Value::Section(Section::from_iter([("a".to_owned(), value)]));
+    }
+
+    value
+}
+
+/// A section with arrays that are nested `levels` deep, counting the section.
+fn nested_arrays(levels: usize) -> Value {
+    let mut value = Value::Array {
+        element: ElementType::U8,
+        values: vec![Value::U8(1)],
+    };
+    for _ in 2..levels {
+        value = Value::Array {
+            element: ElementType::Array,
+            values: vec![value],
+        };
+    }
+
+    Value::Section(Section::from_iter([("a".to_owned(), value)]))
+}
+
+#[test]
+fn deep_nesting_is_not_serialized() {
+    for value in [nested_sections(100), nested_arrays(100)] {
+        let data = to_bytes(&value).unwrap();
+        assert_eq!(from_bytes::<Value, _>(&data).unwrap(), value);
+    }
+
+    for value in [nested_sections(101), nested_arrays(101)] {
+        let err = to_bytes(&value).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Sections and arrays must not be nested more than 100 levels deep"
+        );
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, PartialEq)]
+struct TestAddresses {
+    v4: Ipv4Addr,
+    v6: Ipv6Addr,
+}
+
+#[test]
+fn addresses_are_compact() {
+    let obj = TestAddresses {
+        v4: Ipv4Addr::new(127, 0, 0, 1),
+        v6: Ipv6Addr::LOCALHOST,
+    };
+    let data = to_bytes(&obj).unwrap();
+    assert_eq!(
+        &data[9..],
+        b"\x08\x02v4\x0a\x10\x7f\x00\x00\x01\x02v6\x0a\x40\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x01"
+    );
+    assert_eq!(obj, from_bytes(data).unwrap());
+}
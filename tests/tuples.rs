@@ -0,0 +1,163 @@
+mod common;
+
+use common::round_trip;
+use monero_epee_bin_serde::{from_bytes, to_bytes, Config, TupleEncoding};
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+struct Hash([u8; 32]);
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+struct Rgb(u8, u8, u8);
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+struct Pair(u64, String);
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+struct Test {
+    hash: Hash,
+    color: Rgb,
+    pair: Pair,
+    pairs: Vec<Pair>,
+    colors: Vec<Rgb>,
+}
+
+#[test]
+fn byte_tuple_struct_is_a_blob() {
+    #[derive(Serialize)]
+    struct Test {
+        color: Rgb,
+    }
+
+    let buf = to_bytes(&Test {
+        color: Rgb(1, 2, 3),
+    })
+    .unwrap();
+
+    assert_eq!(&buf[9..], b"\x04\x05color\x0a\x0c\x01\x02\x03");
+}
+
+#[test]
+fn tuple_struct_is_a_section_with_positional_fields() {
+    #[derive(Serialize)]
+    struct Test {
+        pair: Pair,
+    }
+
+    let buf = to_bytes(&Test {
+        pair: Pair(1, "a".to_owned()),
+    })
+    .unwrap();
+
+    assert_eq!(
+        &buf[9..],
+        b"\x04\x04pair\x0c\x08\x010\x05\x01\x00\x00\x00\x00\x00\x00\x00\x011\x0a\x04a"
+    );
+}
+
+#[test]
+fn tuple_structs_round_trip() {
+    round_trip(&Test {
+        hash: Hash([7; 32]),
+        color: Rgb(1, 2, 3),
+        pair: Pair(u64::MAX, "foo".to_owned()),
+        pairs: vec![Pair(1, "a".to_owned()), Pair(2, "b".to_owned())],
+        colors: vec![Rgb(1, 2, 3), Rgb(4, 5, 6)],
+    });
+}
+
+#[test]
+fn tuple_struct_can_be_root() {
+    round_trip(&Pair(1, "a".to_owned()));
+}
+
+#[test]
+fn tuple_struct_length_mismatch() {
+    #[derive(Serialize)]
+    struct Test {
+        color: [u8; 4],
+    }
+
+    #[derive(Debug, Deserialize)]
+    struct Expected {
+        #[allow(dead_code)]
+        color: Rgb,
+    }
+
+    let buf = to_bytes(&Test { color: [0; 4] }).unwrap();
+    from_bytes::<Expected, _>(buf).unwrap_err();
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+struct Tuples {
+    pair: (u64, u64),
+    triple: [u32; 3],
+    hash: [u8; 4],
+}
+
+#[test]
+fn homogeneous_tuple_is_a_typed_array() {
+    let val = Tuples {
+        pair: (1, 2),
+        triple: [3, 4, 5],
+        hash: [6, 7, 8, 9],
+    };
+
+    let buf = to_bytes(&val).unwrap();
+    assert_eq!(
+        &buf[9..],
+        b"\x0c\x04pair\x85\x08\x01\x00\x00\x00\x00\x00\x00\x00\x02\x00\x00\x00\x00\x00\x00\x00\x06triple\x86\x0c\x03\x00\x00\x00\x04\x00\x00\x00\x05\x00\x00\x00\x04hash\x0a\x10\x06\x07\x08\x09"
+    );
+
+    round_trip(&val);
+}
+
+#[test]
+fn tuple_length_is_checked() {
+    #[derive(Serialize)]
+    struct Test {
+        pair: [u64; 3],
+    }
+
+    #[derive(Debug, Deserialize)]
+    struct Expected {
+        #[allow(dead_code)]
+        pair: (u64, u64),
+    }
+
+    let buf = to_bytes(&Test { pair: [1, 2, 3] }).unwrap();
+    from_bytes::<Expected, _>(buf).unwrap_err();
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+enum Message {
+    Pair(u64, String),
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+struct Heterogeneous {
+    pair: (u64, String),
+    pairs: Vec<(u8, bool)>,
+    message: Message,
+    hash: [u8; 4],
+}
+
+#[test]
+fn heterogeneous_tuples_are_sections() {
+    let config = Config::new().tuple_encoding(TupleEncoding::Section);
+    let val = Heterogeneous {
+        pair: (1, "a".to_owned()),
+        pairs: vec![(1, true), (2, false)],
+        message: Message::Pair(2, "b".to_owned()),
+        hash: [1, 2, 3, 4],
+    };
+
+    let buf = config.to_bytes(&val).unwrap();
+    assert_eq!(
+        &buf[9..28],
+        b"\x10\x04pair\x0c\x08\x010\x05\x01\x00\x00\x00\x00\x00\x00\x00"
+    );
+
+    let val2 = from_bytes(buf).unwrap();
+    assert_eq!(val, val2);
+}
@@ -0,0 +1,303 @@
+use monero_epee_bin_serde::value::{ElementType, Section, UnknownFields, Value, WithUnknownFields};
+use monero_epee_bin_serde::{from_bytes, to_bytes, Config};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Deserialize, Serialize, PartialEq)]
+struct NodeDataV2 {
+    my_port: u32,
+    peer_id: u64,
+    support_flags: u32,
+    rpc_port: u16,
+    tags: Vec<String>,
+    extra: Inner,
+}
+
+#[derive(Debug, Deserialize, Serialize, PartialEq)]
+struct Inner {
+    flag: bool,
+    level: i8,
+}
+
+#[derive(Debug, Deserialize, Serialize, PartialEq)]
+struct NodeDataV1 {
+    my_port: u32,
+    peer_id: u64,
+    #[serde(flatten)]
+    unknown: UnknownFields,
+}
+
+fn node_data() -> NodeDataV2 {
+    NodeDataV2 {
+        my_port: 18080,
+        peer_id: 3754955098988524350,
+        support_flags: 1,
+        rpc_port: 18081,
+        tags: vec!["a".to_owned(), "b".to_owned()],
+        extra: Inner {
+            flag: true,
+            level: -3,
+        },
+    }
+}
+
+#[test]
+fn unknown_fields_are_preserved() {
+    let bytes = to_bytes(&node_data()).unwrap();
+
+    let old: NodeDataV1 = from_bytes(&bytes).unwrap();
+    assert_eq!(old.my_port, 18080);
+    assert_eq!(old.unknown.len(), 4);
+    assert_eq!(old.unknown.get("support_flags"), Some(&Value::U32(1)));
+    assert_eq!(old.unknown.get("rpc_port"), Some(&Value::U16(18081)));
+
+    assert_eq!(to_bytes(&old).unwrap(), bytes);
+}
+
+#[test]
+fn values_round_trip_with_their_markers() {
+    let bytes = to_bytes(&node_data()).unwrap();
+
+    let section: Section = from_bytes(&bytes).unwrap();
+    assert_eq!(
+        section.get("tags"),
+        Some(&Value::Array {
+            element: ElementType::String,
+            values: vec![Value::String(b"a".to_vec()), Value::String(b"b".to_vec())]
+        })
+    );
+    assert_eq!(to_bytes(&section).unwrap(), bytes);
+
+    let value: Value = from_bytes(&bytes).unwrap();
+    assert_eq!(value, Value::Section(section));
+    assert_eq!(to_bytes(&value).unwrap(), bytes);
+}
+
+#[test]
+fn nested_arrays_round_trip_as_values() {
+    #[derive(Serialize)]
+    struct Nested {
+        blobs: Vec<Vec<u8>>,
+        arrays: Vec<Vec<u64>>,
+    }
+
+    let bytes = to_bytes(&Nested {
+        blobs: vec![vec![1], vec![2, 3]],
+        arrays: vec![vec![4], vec![]],
+    })
+    .unwrap();
+
+    let section: Section = from_bytes(&bytes).unwrap();
+    assert_eq!(
+        section.get("arrays"),
+        Some(&Value::Array {
+            element: ElementType::Array,
+            values: vec![
+                Value::Array {
+                    element: ElementType::U64,
+                    values: vec![Value::U64(4)]
+                },
+                Value::Array {
+                    element: ElementType::U64,
+                    values: vec![]
+                }
+            ]
+        })
+    );
+    assert_eq!(to_bytes(&section).unwrap(), bytes);
+    assert_eq!(Config::new().from_slice_lenient(&bytes).section, section);
+}
+
+#[test]
+fn typed_arrays_round_trip() {
+    let section = Section::from_iter([
+        (
+            "bytes".to_owned(),
+            Value::Array {
+                element: ElementType::U8,
+                values: vec![Value::U8(1), Value::U8(2)],
+            },
+        ),
+        (
+            "empty".to_owned(),
+            Value::Array {
+                element: ElementType::U64,
+                values: vec![],
+            },
+        ),
+        (
+            "nested".to_owned(),
+            Value::Array {
+                element: ElementType::Array,
+                values: vec![Value::Array {
+                    element: ElementType::Section,
+                    values: vec![],
+                }],
+            },
+        ),
+    ]);
+
+    let bytes = to_bytes(&section).unwrap();
+    assert_eq!(
+        &bytes[9..],
+        b"\x0c\x05bytes\x88\x08\x01\x02\x05empty\x85\x00\x06nested\x8d\x04\x8c\x00"
+    );
+
+    assert_eq!(from_bytes::<Section, _>(&bytes).unwrap(), section);
+    assert_eq!(Config::new().from_slice_lenient(&bytes).section, section);
+}
+
+#[test]
+fn typed_arrays_check_their_elements() {
+    let section = Section::from_iter([(
+        "numbers".to_owned(),
+        Value::Array {
+            element: ElementType::U64,
+            values: vec![Value::U32(1)],
+        },
+    )]);
+
+    let err = to_bytes(&section).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Array elements must have the same type, expected Single(5) but got Single(6)"
+    );
+}
+
+#[test]
+fn no_unknown_fields() {
+    let old = NodeDataV1 {
+        my_port: 1,
+        peer_id: 2,
+        unknown: UnknownFields::new(),
+    };
+
+    let bytes = to_bytes(&old).unwrap();
+    assert_eq!(from_bytes::<NodeDataV1, _>(&bytes).unwrap(), old);
+}
+
+#[derive(Debug, Deserialize, Serialize, PartialEq)]
+struct Known {
+    my_port: u32,
+    peer_id: u64,
+}
+
+#[test]
+fn unknown_fields_are_captured_with_their_type() {
+    let bytes = to_bytes(&node_data()).unwrap();
+
+    let old: WithUnknownFields<Known> = from_bytes(&bytes).unwrap();
+    assert_eq!(
+        old.known,
+        Known {
+            my_port: 18080,
+            peer_id: 3754955098988524350,
+        }
+    );
+    assert_eq!(old.unknown.len(), 4);
+    assert_eq!(old.unknown.get("rpc_port"), Some(&Value::U16(18081)));
+
+    assert_eq!(to_bytes(&old).unwrap(), bytes);
+}
+
+fn with_empty_array() -> Vec<u8> {
+    let section = Section::from_iter([
+        ("my_port".to_owned(), Value::U32(18080)),
+        ("peer_id".to_owned(), Value::U64(1)),
+        (
+            "peers".to_owned(),
+            Value::Array {
+                element: ElementType::U64,
+                values: vec![],
+            },
+        ),
+    ]);
+
+    to_bytes(&section).unwrap()
+}
+
+#[test]
+fn empty_arrays_keep_their_type() {
+    let bytes = with_empty_array();
+    assert!(bytes.ends_with(b"\x05peers\x85\x00"));
+
+    let old: WithUnknownFields<Known> = from_bytes(&bytes).unwrap();
+    assert_eq!(
+        old.unknown.get("peers"),
+        Some(&Value::Array {
+            element: ElementType::U64,
+            values: vec![]
+        })
+    );
+    assert_eq!(to_bytes(&old).unwrap(), bytes);
+}
+
+#[test]
+fn flattened_empty_arrays_are_not_serialized() {
+    let old: NodeDataV1 = from_bytes(with_empty_array()).unwrap();
+    assert_eq!(
+        old.unknown.get("peers"),
+        Some(&Value::Array {
+            element: ElementType::Untyped,
+            values: vec![]
+        })
+    );
+
+    let err = to_bytes(&old).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Arrays must have an element type, capture unknown fields with `WithUnknownFields` to keep it"
+    );
+}
+
+#[test]
+fn nested_unknown_fields_are_captured() {
+    #[derive(Debug, Deserialize, Serialize, PartialEq)]
+    struct Outer {
+        my_port: u32,
+        extra: WithUnknownFields<Level>,
+    }
+
+    #[derive(Debug, Deserialize, Serialize, PartialEq)]
+    struct Level {
+        level: i8,
+    }
+
+    let bytes = to_bytes(&node_data()).unwrap();
+
+    let old: WithUnknownFields<Outer> = from_bytes(&bytes).unwrap();
+    assert_eq!(old.known.extra.known, Level { level: -3 });
+    assert_eq!(
+        old.known.extra.unknown.iter().collect::<Vec<_>>(),
+        [("flag", &Value::Bool(true))]
+    );
+    assert_eq!(
+        old.unknown.iter().map(|(name, _)| name).collect::<Vec<_>>(),
+        ["peer_id", "support_flags", "rpc_port", "tags"]
+    );
+
+    let strict = Config::new().strict(true);
+    assert_eq!(
+        strict
+            .from_bytes::<WithUnknownFields<Outer>, _>(&bytes)
+            .unwrap(),
+        old
+    );
+}
+
+#[test]
+fn unknown_fields_in_other_formats() {
+    let old = WithUnknownFields {
+        known: Known {
+            my_port: 1,
+            peer_id: 2,
+        },
+        unknown: Section::from_iter([("rpc_port".to_owned(), Value::U16(3))]),
+    };
+
+    let json = serde_json::to_string(&old).unwrap();
+    assert_eq!(json, r#"{"my_port":1,"peer_id":2,"rpc_port":3}"#);
+
+    let new: WithUnknownFields<Known> = serde_json::from_str(&json).unwrap();
+    assert_eq!(new.known, old.known);
+    assert!(new.unknown.is_empty());
+}